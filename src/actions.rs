@@ -0,0 +1,346 @@
+use std::{collections::HashSet, fs::File, io::{BufRead, BufReader}};
+
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::settings::Settings;
+
+const STICKPRESS: f32 = 0.5; // How far a stick has to lean to press a move action, for menus.
+
+/// What the player wants to do, independent of the key that says so.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    Confirm,
+    Cancel,
+    Menu,    // Opens the party menu.
+    Accept,  // Takes a recruit's offer.
+    UseItem, // Drinks a healing item.
+    Restart, // Starts over from the game over screen.
+    SwitchFormula, // Flips between the damage formulas, only in debug builds.
+}
+
+impl Action {
+    const ALL: [Action; 12] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::Confirm,
+        Action::Cancel,
+        Action::Menu,
+        Action::Accept,
+        Action::UseItem,
+        Action::Restart,
+        Action::SwitchFormula,
+    ];
+
+    /// The name used in `assets/bindings.txt`.
+    fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Sprint => "sprint",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::Menu => "menu",
+            Action::Accept => "accept",
+            Action::UseItem => "use_item",
+            Action::Restart => "restart",
+            Action::SwitchFormula => "switch_formula",
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        use Binding::{Button, Key};
+        use GamepadButtonType::*;
+
+        match self {
+            Action::MoveUp => vec![Key(KeyCode::W), Key(KeyCode::Up), Button(DPadUp)],
+            Action::MoveDown => vec![Key(KeyCode::S), Key(KeyCode::Down), Button(DPadDown)],
+            Action::MoveLeft => vec![Key(KeyCode::A), Key(KeyCode::Left), Button(DPadLeft)],
+            Action::MoveRight => vec![Key(KeyCode::D), Key(KeyCode::Right), Button(DPadRight)],
+            Action::Sprint => vec![Key(KeyCode::LShift), Button(RightTrigger2)],
+            Action::Confirm => vec![Key(KeyCode::Return), Button(South)],
+            Action::Cancel => vec![Key(KeyCode::Escape), Key(KeyCode::Back), Button(East)],
+            Action::Menu => vec![Key(KeyCode::P), Button(Start)],
+            Action::Accept => vec![Key(KeyCode::Y), Button(North)],
+            Action::UseItem => vec![Key(KeyCode::H), Button(West)],
+            Action::Restart => vec![Key(KeyCode::R), Button(Select)],
+            Action::SwitchFormula => vec![Key(KeyCode::F2)],
+        }
+    }
+}
+
+/// A key or a gamepad button that triggers an action. Buttons work on every connected gamepad.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+impl Binding {
+    /// Keys go by their `KeyCode` name, buttons by their `GamepadButtonType` name after `Pad:`.
+    fn parse(name: &str) -> Option<Binding> {
+        match name.strip_prefix("Pad:") {
+            Some(button) => BUTTONS
+                .iter()
+                .copied()
+                .find(|known| format!("{:?}", known).eq_ignore_ascii_case(button.trim()))
+                .map(Binding::Button),
+            None => KEYS
+                .iter()
+                .copied()
+                .find(|known| format!("{:?}", known).eq_ignore_ascii_case(name))
+                .map(Binding::Key),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Button(button) => format!("{:?}", button),
+        }
+    }
+}
+
+/// Keys that can be bound, looked up by their `KeyCode` name.
+const KEYS: [KeyCode; 63] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Return, KeyCode::Space, KeyCode::Escape, KeyCode::Back, KeyCode::Tab,
+    KeyCode::LShift, KeyCode::RShift, KeyCode::LControl, KeyCode::RControl, KeyCode::LAlt, KeyCode::RAlt,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6, KeyCode::F7,
+    KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+];
+
+const BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South, GamepadButtonType::East, GamepadButtonType::North, GamepadButtonType::West,
+    GamepadButtonType::C, GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger, GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger, GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select, GamepadButtonType::Start, GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb, GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp, GamepadButtonType::DPadDown, GamepadButtonType::DPadLeft, GamepadButtonType::DPadRight,
+];
+
+/// The state of every `Action` this frame. Systems ask this instead of the keyboard or gamepads,
+/// so the bindings can change at any time.
+#[derive(Default)]
+pub struct Actions {
+    bindings: Vec<(Action, Binding)>,
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    stick_pressed: HashSet<Action>, // Move actions pressed by leaning a stick, to find new presses.
+    movement: Vec2,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Eats this frame's press, so systems running later don't act on it again.
+    pub fn clear_just_pressed(&mut self, action: Action) {
+        self.just_pressed.remove(&action);
+    }
+
+    /// Where to walk, each axis between -1 and 1. Keys give full steps, sticks anything in between.
+    pub fn movement(&self) -> Vec2 {
+        self.movement
+    }
+
+    pub fn bindings(&self, action: Action) -> Vec<Binding> {
+        self.bindings.iter().filter(|(bound, _)| *bound == action).map(|(_, binding)| *binding).collect()
+    }
+
+    /// Adds `binding` to `action`. A key or button only ever triggers one action, so it leaves any other.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        self.unbind(binding);
+        self.bindings.push((action, binding));
+    }
+
+    /// Replaces all bindings of `action` with `bindings`.
+    pub fn rebind(&mut self, action: Action, bindings: &[Binding]) {
+        self.bindings.retain(|(bound, _)| *bound != action);
+        for binding in bindings {
+            self.bind(action, *binding);
+        }
+    }
+
+    pub fn unbind(&mut self, binding: Binding) {
+        self.bindings.retain(|(_, bound)| *bound != binding);
+    }
+
+    /// The first key of `action` for on screen hints, like `RETURN`.
+    pub fn key_name(&self, action: Action) -> String {
+        let bindings = self.bindings(action);
+        let binding = bindings
+            .iter()
+            .find(|binding| matches!(binding, Binding::Key(_)))
+            .or_else(|| bindings.first());
+
+        match binding {
+            Some(binding) => binding.name().to_uppercase(),
+            None => "UNBOUND".to_string(),
+        }
+    }
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_bindings)
+            .add_system_to_stage(CoreStage::PreUpdate, update_actions.after(InputSystem))
+            .add_system(log_gamepads);
+    }
+}
+
+fn load_bindings(mut commands: Commands) {
+    let mut actions = Actions::default();
+    for action in Action::ALL {
+        actions.rebind(action, &action.default_bindings());
+    }
+
+    // Actions missing from the file keep their default keys.
+    if let Ok(file) = File::open("assets/bindings.txt") {
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, bindings) = match line.split_once('=') {
+                Some((name, bindings)) => (name.trim(), bindings),
+                None => {
+                    println!("Invalid binding: {}", line);
+                    continue;
+                }
+            };
+            let action = match Action::ALL.iter().find(|action| action.name() == name) {
+                Some(action) => *action,
+                None => {
+                    println!("Unknown action: {}", name);
+                    continue;
+                }
+            };
+
+            let bindings: Vec<Binding> = bindings
+                .split(',')
+                .filter_map(|binding| {
+                    let parsed = Binding::parse(binding.trim());
+                    if parsed.is_none() {
+                        println!("Unknown key or button: {}", binding.trim());
+                    }
+                    parsed
+                })
+                .collect();
+            actions.rebind(action, &bindings);
+        }
+    }
+
+    commands.insert_resource(actions);
+}
+
+fn update_actions(
+    keyboard: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    settings: Res<Settings>,
+    mut actions: ResMut<Actions>,
+) {
+    let mut pressed = HashSet::new();
+    let mut just_pressed = HashSet::new();
+    for (action, binding) in actions.bindings.iter() {
+        let (down, new) = match binding {
+            Binding::Key(key) => (keyboard.pressed(*key), keyboard.just_pressed(*key)),
+            Binding::Button(button) => gamepads.iter().fold((false, false), |(down, new), gamepad| {
+                let button = GamepadButton(*gamepad, *button);
+                (down || buttons.pressed(button), new || buttons.just_pressed(button))
+            }),
+        };
+
+        if down {
+            pressed.insert(*action);
+        }
+        if new {
+            just_pressed.insert(*action);
+        }
+    }
+
+    let axis = |action_a: Action, action_b: Action| {
+        pressed.contains(&action_a) as i32 as f32 - pressed.contains(&action_b) as i32 as f32
+    };
+    let digital = Vec2::new(axis(Action::MoveRight, Action::MoveLeft), axis(Action::MoveUp, Action::MoveDown));
+
+    // The first stick leaning past the deadzone wins, whichever gamepad it is on.
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| {
+            let x = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+            let y = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+            apply_deadzone(Vec2::new(x, y), settings.stick_deadzone)
+        })
+        .find(|stick| *stick != Vec2::ZERO)
+        .unwrap_or(Vec2::ZERO);
+
+    // Leaning the stick far enough also presses the move actions, so it can drive the menus.
+    let mut stick_pressed = HashSet::new();
+    for (action, lean) in [
+        (Action::MoveUp, stick.y),
+        (Action::MoveDown, -stick.y),
+        (Action::MoveLeft, -stick.x),
+        (Action::MoveRight, stick.x),
+    ] {
+        if lean < STICKPRESS {
+            continue;
+        }
+        if !actions.stick_pressed.contains(&action) && !pressed.contains(&action) {
+            just_pressed.insert(action);
+        }
+        pressed.insert(action);
+        stick_pressed.insert(action);
+    }
+
+    actions.movement = if stick != Vec2::ZERO { stick } else { digital };
+    actions.pressed = pressed;
+    actions.just_pressed = just_pressed;
+    actions.stick_pressed = stick_pressed;
+}
+
+/// Ignores small leans and rescales the rest, so walking starts slowly right at the edge of the deadzone.
+fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let lean = stick.length();
+    if lean <= deadzone {
+        return Vec2::ZERO;
+    }
+    stick / lean * ((lean - deadzone) / (1.0 - deadzone)).min(1.0)
+}
+
+/// Gamepads can come and go at any time, their buttons and sticks work as soon as they are connected.
+fn log_gamepads(mut gamepad_event: EventReader<GamepadEvent>) {
+    for GamepadEvent(gamepad, event) in gamepad_event.iter() {
+        match event {
+            GamepadEventType::Connected => println!("Gamepad {} connected", gamepad.0),
+            GamepadEventType::Disconnected => println!("Gamepad {} disconnected", gamepad.0),
+            _ => {}
+        }
+    }
+}
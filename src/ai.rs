@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use rand::{Rng, rngs::StdRng};
+
+use crate::combat::CombatStats;
+
+/// A snapshot of someone in the fight, as seen by an enemy deciding what to do.
+#[derive(Clone, Copy)]
+pub struct Combatant {
+    pub entity: Entity,
+    pub stats: CombatStats,
+}
+
+impl Combatant {
+    fn health_fraction(&self) -> f32 {
+        if self.stats.max_health <= 0 {
+            return 0.0;
+        }
+        self.stats.health as f32 / self.stats.max_health as f32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyAction {
+    Attack(Entity),
+    Guard,
+    Heal(Entity, isize),
+    Flee,
+}
+
+/// Picks an enemy's action each turn. All randomness has to come from `rng` so fights replay from a seed.
+pub trait Behavior: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// `allies` are the living members of the enemy's side, including `me`, `foes` the living members of the other.
+    fn choose(&self, me: &Combatant, allies: &[Combatant], foes: &[Combatant], rng: &mut StdRng) -> EnemyAction;
+}
+
+/// The behavior an enemy fights with.
+#[derive(Component)]
+pub struct EnemyAi(pub Box<dyn Behavior>);
+
+/// Always goes for the foe with the least health left.
+pub struct Aggressive;
+
+impl Behavior for Aggressive {
+    fn name(&self) -> &'static str {
+        "aggressive"
+    }
+
+    fn choose(&self, _me: &Combatant, _allies: &[Combatant], foes: &[Combatant], _rng: &mut StdRng) -> EnemyAction {
+        match foes.iter().min_by_key(|foe| foe.stats.health) {
+            Some(foe) => EnemyAction::Attack(foe.entity),
+            None => EnemyAction::Guard,
+        }
+    }
+}
+
+/// Attacks, but tends to guard once its health runs low.
+pub struct Defensive {
+    pub threshold: f32, // Fraction of max health below which it considers guarding.
+    pub guard_chance: f64,
+}
+
+impl Default for Defensive {
+    fn default() -> Self {
+        Defensive {
+            threshold: 0.5,
+            guard_chance: 0.6,
+        }
+    }
+}
+
+impl Behavior for Defensive {
+    fn name(&self) -> &'static str {
+        "defensive"
+    }
+
+    fn choose(&self, me: &Combatant, _allies: &[Combatant], foes: &[Combatant], rng: &mut StdRng) -> EnemyAction {
+        if me.health_fraction() < self.threshold && rng.gen_bool(self.guard_chance) {
+            return EnemyAction::Guard;
+        }
+        attack_random(foes, rng)
+    }
+}
+
+/// Heals the most hurt ally, itself included, and only attacks when nobody needs it.
+pub struct Healer {
+    pub threshold: f32,
+    pub power: isize,
+}
+
+impl Default for Healer {
+    fn default() -> Self {
+        Healer {
+            threshold: 0.5,
+            power: 3,
+        }
+    }
+}
+
+impl Behavior for Healer {
+    fn name(&self) -> &'static str {
+        "healer"
+    }
+
+    fn choose(&self, _me: &Combatant, allies: &[Combatant], foes: &[Combatant], rng: &mut StdRng) -> EnemyAction {
+        let hurt = allies
+            .iter()
+            .filter(|ally| ally.health_fraction() < self.threshold)
+            .min_by(|a, b| a.health_fraction().total_cmp(&b.health_fraction()));
+
+        match hurt {
+            Some(ally) => EnemyAction::Heal(ally.entity, self.power),
+            None => attack_random(foes, rng),
+        }
+    }
+}
+
+/// Tries to run away once it is hurt.
+pub struct Coward {
+    pub threshold: f32,
+}
+
+impl Default for Coward {
+    fn default() -> Self {
+        Coward { threshold: 0.5 }
+    }
+}
+
+impl Behavior for Coward {
+    fn name(&self) -> &'static str {
+        "coward"
+    }
+
+    fn choose(&self, me: &Combatant, _allies: &[Combatant], foes: &[Combatant], rng: &mut StdRng) -> EnemyAction {
+        if me.health_fraction() < self.threshold {
+            return EnemyAction::Flee;
+        }
+        attack_random(foes, rng)
+    }
+}
+
+/// Looks up a behavior by its `name`, at its default tuning.
+pub fn behavior(name: &str) -> Option<Box<dyn Behavior>> {
+    let behaviors: [Box<dyn Behavior>; 4] = [
+        Box::new(Aggressive),
+        Box::new(Defensive::default()),
+        Box::new(Healer::default()),
+        Box::new(Coward::default()),
+    ];
+    behaviors.into_iter().find(|behavior| behavior.name() == name)
+}
+
+fn attack_random(foes: &[Combatant], rng: &mut StdRng) -> EnemyAction {
+    if foes.is_empty() {
+        return EnemyAction::Guard;
+    }
+    EnemyAction::Attack(foes[rng.gen_range(0..foes.len())].entity)
+}
@@ -0,0 +1,115 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::GameState;
+
+const LUNGEDURATION: f32 = 0.3;
+const LUNGEDISTANCE: f32 = 0.15;
+const SHAKEDURATION: f32 = 0.4;
+const SHAKEDISTANCE: f32 = 0.04;
+const SHAKESPEED: f32 = 50.0;
+const FLASHCOLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+const FADEDURATION: f32 = 0.6;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationKind {
+    Lunge(Vec3), // Towards the given direction and back.
+    Shake, // Wiggles and flashes, for taking a hit.
+    FadeOut, // For defeated enemies, they stay hidden afterwards.
+}
+
+impl AnimationKind {
+    fn duration(&self) -> f32 {
+        match self {
+            AnimationKind::Lunge(_) => LUNGEDURATION,
+            AnimationKind::Shake => SHAKEDURATION,
+            AnimationKind::FadeOut => FADEDURATION,
+        }
+    }
+}
+
+/// A short tween playing on a combat sprite, combat waits until none are left.
+#[derive(Component)]
+pub struct CombatAnimation {
+    kind: AnimationKind,
+    timer: Timer,
+}
+
+/// Where an animated entity sits when it is not animating.
+#[derive(Component)]
+struct RestPosition(Vec3);
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Combat).with_system(animate_combatants));
+    }
+}
+
+/// Starts `kind` on `entity`, replacing whatever it was playing.
+pub fn play_animation(commands: &mut Commands, entity: Entity, kind: AnimationKind) {
+    commands.entity(entity).insert(CombatAnimation {
+        kind,
+        timer: Timer::from_seconds(kind.duration(), false),
+    });
+}
+
+/// Combatants with an animation running, player sprites have no `Sprite` and enemies no `Visibility` to fade.
+type Animated<'w, 's> = Query<'w, 's, (
+    Entity,
+    &'static mut CombatAnimation,
+    &'static mut Transform,
+    Option<&'static RestPosition>,
+    Option<&'static mut Sprite>,
+    Option<&'static mut Visibility>,
+)>;
+
+fn animate_combatants(
+    mut commands: Commands,
+    mut animation_query: Animated,
+    time: Res<Time>,
+) {
+    for (entity, mut animation, mut transform, rest, sprite, visibility) in animation_query.iter_mut() {
+        let rest = match rest {
+            Some(rest) => rest.0,
+            None => {
+                commands.entity(entity).insert(RestPosition(transform.translation));
+                transform.translation
+            }
+        };
+
+        animation.timer.tick(time.delta());
+        let t = animation.timer.percent();
+
+        match animation.kind {
+            AnimationKind::Lunge(direction) => {
+                transform.translation = rest + direction.normalize_or_zero() * LUNGEDISTANCE * (t * PI).sin();
+            }
+            AnimationKind::Shake => {
+                let offset = (t * SHAKESPEED).sin() * SHAKEDISTANCE * (1.0 - t);
+                transform.translation = rest + Vec3::new(offset, 0.0, 0.0);
+                if let Some(mut sprite) = sprite {
+                    let flashing = (t * 4.0).fract() < 0.5 && !animation.timer.finished();
+                    sprite.color = if flashing { FLASHCOLOR } else { Color::WHITE };
+                }
+            }
+            AnimationKind::FadeOut => {
+                if let Some(mut sprite) = sprite {
+                    sprite.color = Color::rgba(1.0, 1.0, 1.0, 1.0 - t);
+                }
+                if animation.timer.finished() {
+                    if let Some(mut visibility) = visibility {
+                        visibility.is_visible = false;
+                    }
+                }
+            }
+        }
+
+        if animation.timer.finished() {
+            transform.translation = rest;
+            commands.entity(entity).remove::<CombatAnimation>();
+        }
+    }
+}
@@ -4,6 +4,9 @@ use crate::TILESIZE;
 
 pub struct AsciiSheet(pub Handle<TextureAtlas>);
 
+#[derive(Component)]
+pub struct AsciiText;
+
 pub struct AsciiPlugin;
 
 impl Plugin for AsciiPlugin {
@@ -35,6 +38,40 @@ pub fn spawn_ascii_sprite(
         }).id()
 }
 
+/// Spawns a line of text, one sprite per character, starting at `left_center`.
+pub fn spawn_ascii_text(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    to_print: &str,
+    color: Color,
+    left_center: Vec3,
+) -> Entity {
+    let mut character_sprites = Vec::new();
+    for (i, char) in to_print.chars().enumerate() {
+        // The sheet only holds 256 characters.
+        assert!((char as usize) < 256);
+        character_sprites.push(spawn_ascii_sprite(
+            commands,
+            ascii,
+            char as usize,
+            color,
+            Vec3::new(i as f32 * TILESIZE, 0.0, 0.0),
+        ));
+    }
+
+    commands
+        .spawn()
+        .insert(Name::new(format!("Text - {}", to_print)))
+        .insert(Transform {
+            translation: left_center,
+            ..default()
+        })
+        .insert(GlobalTransform::default())
+        .insert(AsciiText)
+        .push_children(&character_sprites)
+        .id()
+}
+
 fn load_ascii(
     mut commands: Commands,
     assets: Res<AssetServer>,
@@ -0,0 +1,446 @@
+//! Runs automated fights against every enemy to check the balance, without opening a window.
+//! Every turn is played by the same rules as in `combat.rs`, only the player's choices come from a simple script.
+//!
+//! cargo run --bin simulator -- --fights 5000 --level 3 --seed 42 --formula classic --mode atb --terrain grass
+use std::{collections::{BTreeMap, VecDeque}, env, process};
+
+use bevy::prelude::Entity;
+use rand::{SeedableRng, rngs::StdRng};
+
+use bevy_tutorial::{
+    ai::{Combatant, EnemyAi},
+    combat::{
+        AtbGauge, CombatStats, Enemy, EnemyMove, EnemyType, FightEvent,
+        attack_event, begin_turn, enemy_move, land_hit, skill_events, speed_order,
+    },
+    combat_menu::CombatAction,
+    damage::{ClassicFormula, DamageFormula, StandardFormula},
+    element::{Affinities, DamageType},
+    experience::read_level_curve,
+    player::starting_stats,
+    settings::CombatMode,
+    skills::{SkillBook, SkillEffect, read_skills},
+    status::StatusEffects,
+    terrain::Terrain,
+};
+
+const MAXTURNS: usize = 100; // Fights that take longer count as a draw.
+const FRAME: f32 = 1.0 / 60.0; // Time step of active time battles.
+const MAXWAIT: usize = 3600; // Frames without anyone acting before an active time battle counts as a draw.
+
+struct Config {
+    fights: usize,
+    seed: u64,
+    formula: Box<dyn DamageFormula>,
+    stats: CombatStats,
+    level: usize,
+    mode: CombatMode,
+    terrain: Terrain,
+}
+
+#[derive(Default)]
+struct Report {
+    wins: usize,
+    losses: usize,
+    fled: usize,
+    turns: usize,
+    skills: usize, // Skills the player used.
+    dealt: BTreeMap<isize, usize>, // Damage per hit on the enemy and how often it happened.
+    taken: BTreeMap<isize, usize>,
+}
+
+enum Outcome {
+    Win,
+    Loss,
+    Fled,
+    Draw,
+}
+
+/// Someone in the fight, with the components the game would give it.
+struct Fighter {
+    entity: Entity,
+    stats: CombatStats,
+    effects: StatusEffects,
+    affinities: Affinities,
+    gauge: AtbGauge,
+    enemy: Option<(Enemy, EnemyAi)>,
+}
+
+// Stand-ins for the entities, they double as indices into the fighters.
+const PLAYER: usize = 0;
+const ENEMY: usize = 1;
+
+fn main() {
+    let config = parse_args().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("Usage: simulator [--fights N] [--seed N] [--formula standard|classic] [--level N]");
+        eprintln!("                 [--mode turn_based|atb] [--terrain ground|grass|water]");
+        eprintln!("                 [--health N] [--attack N] [--defense N] [--mana N] [--speed N]");
+        process::exit(1);
+    });
+
+    let book = read_skills();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    println!(
+        "Player level {}: {} hp, {} mp, {} attack, {} defense, {} speed. {} formula, {:?} on {}, {} fights per enemy, seed {}.",
+        config.level,
+        config.stats.max_health,
+        config.stats.max_mana,
+        config.stats.attack,
+        config.stats.defense,
+        config.stats.speed,
+        config.formula.name(),
+        config.mode,
+        config.terrain.name(),
+        config.fights,
+        config.seed,
+    );
+
+    for enemy_type in EnemyType::ALL {
+        let mut report = Report::default();
+        for _ in 0..config.fights {
+            match fight(&config, &book, enemy_type, &mut rng, &mut report) {
+                Outcome::Win => report.wins += 1,
+                Outcome::Loss => report.losses += 1,
+                Outcome::Fled => report.fled += 1,
+                Outcome::Draw => (),
+            }
+        }
+        print_report(enemy_type, &config, &report);
+    }
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config {
+        fights: 1000,
+        seed: 0,
+        formula: Box::new(StandardFormula::default()),
+        stats: starting_stats(),
+        level: 1,
+        mode: CombatMode::TurnBased,
+        terrain: Terrain::Plain,
+    };
+    let mut overrides: Vec<(String, isize)> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+        let invalid = |_| format!("Invalid value for {}: {}", flag, value);
+
+        match flag.as_str() {
+            "--fights" => config.fights = value.parse().map_err(invalid)?,
+            "--seed" => config.seed = value.parse().map_err(invalid)?,
+            "--level" => config.level = value.parse().map_err(invalid)?,
+            "--formula" => {
+                config.formula = match value.as_str() {
+                    "standard" => Box::new(StandardFormula::default()),
+                    "classic" => Box::new(ClassicFormula { min_damage: 1 }),
+                    _ => return Err(format!("Unknown formula: {}", value)),
+                }
+            }
+            "--mode" => {
+                config.mode = match value.as_str() {
+                    "turn_based" => CombatMode::TurnBased,
+                    "atb" => CombatMode::Atb,
+                    _ => return Err(format!("Unknown combat mode: {}", value)),
+                }
+            }
+            "--terrain" => {
+                config.terrain = [Terrain::Plain, Terrain::Grass, Terrain::Water]
+                    .into_iter()
+                    .find(|terrain| terrain.name() == value)
+                    .ok_or(format!("Unknown terrain: {}", value))?
+            }
+            "--health" | "--attack" | "--defense" | "--mana" | "--speed" => {
+                overrides.push((flag.clone(), value.parse().map_err(invalid)?))
+            }
+            _ => return Err(format!("Unknown option: {}", flag)),
+        }
+    }
+
+    // Level first, so explicit stats win over the level curve.
+    let curve = read_level_curve();
+    for _ in 1..config.level {
+        curve.level_up(&mut config.stats);
+    }
+    for (flag, value) in overrides {
+        match flag.as_str() {
+            "--health" => {
+                config.stats.health = value;
+                config.stats.max_health = value;
+            }
+            "--mana" => {
+                config.stats.mana = value;
+                config.stats.max_mana = value;
+            }
+            "--attack" => config.stats.attack = value,
+            "--defense" => config.stats.defense = value,
+            _ => config.stats.speed = value,
+        }
+    }
+
+    Ok(config)
+}
+
+/// Plays one fight in turn order, or by the gauges in active time battles, until one side is down.
+fn fight(config: &Config, book: &SkillBook, enemy_type: EnemyType, rng: &mut StdRng, report: &mut Report) -> Outcome {
+    let enemy = Enemy {
+        enemy_type,
+        attack_type: enemy_type.attack_type(),
+    };
+    let mut enemy_stats = enemy_type.stats();
+    config.terrain.empower(&enemy, &mut enemy_stats);
+
+    let mut fighters = [
+        Fighter {
+            entity: Entity::from_raw(PLAYER as u32),
+            stats: config.stats,
+            effects: StatusEffects::default(),
+            affinities: Affinities::default(),
+            gauge: AtbGauge::default(),
+            enemy: None,
+        },
+        Fighter {
+            entity: Entity::from_raw(ENEMY as u32),
+            stats: enemy_stats,
+            effects: StatusEffects::default(),
+            affinities: enemy_type.affinities(),
+            gauge: AtbGauge::default(),
+            enemy: Some((enemy, enemy_type.ai())),
+        },
+    ];
+
+    let mut order: VecDeque<Entity> = VecDeque::new();
+    for _ in 0..MAXTURNS {
+        let actor = match config.mode {
+            CombatMode::TurnBased => {
+                if order.is_empty() {
+                    order = speed_order(living(&fighters).iter()).into();
+                }
+                order.pop_front()
+            }
+            CombatMode::Atb => next_full_gauge(&mut fighters),
+        };
+        let actor = match actor {
+            Some(actor) => actor,
+            None => break,
+        };
+
+        if let Some(outcome) = take_turn(config, book, &mut fighters, actor.id() as usize, rng, report) {
+            return outcome;
+        }
+    }
+
+    Outcome::Draw
+}
+
+fn living(fighters: &[Fighter]) -> Vec<Combatant> {
+    fighters
+        .iter()
+        .filter(|fighter| fighter.stats.health > 0)
+        .map(|fighter| Combatant { entity: fighter.entity, stats: fighter.stats })
+        .collect()
+}
+
+/// Fills the gauges frame by frame, like `fill_atb_gauges`, until someone can act.
+fn next_full_gauge(fighters: &mut [Fighter]) -> Option<Entity> {
+    // Gauges that filled in the same frame as the last one's go next.
+    if let Some(full) = fighters.iter().find(|fighter| fighter.stats.health > 0 && fighter.gauge.0 >= 1.0) {
+        return Some(full.entity);
+    }
+
+    for _ in 0..MAXWAIT {
+        let mut full = None;
+        for fighter in fighters.iter_mut().filter(|fighter| fighter.stats.health > 0) {
+            if fighter.gauge.fill(fighter.stats.speed, FRAME) && full.is_none() {
+                full = Some(fighter.entity);
+            }
+        }
+        if full.is_some() {
+            return full;
+        }
+    }
+    None
+}
+
+/// One combatant's turn, as `take_turns` and `fill_atb_gauges` play it. Returns the outcome once the fight is decided.
+fn take_turn(
+    config: &Config,
+    book: &SkillBook,
+    fighters: &mut [Fighter; 2],
+    actor: usize,
+    rng: &mut StdRng,
+    report: &mut Report,
+) -> Option<Outcome> {
+    // Combatants that fell before their turn came up are skipped.
+    if fighters[actor].stats.health == 0 {
+        return None;
+    }
+    report.turns += 1;
+    fighters[actor].gauge.0 = 0.0;
+
+    let (allies, foes): (Vec<Combatant>, Vec<Combatant>) = living(fighters)
+        .into_iter()
+        .partition(|combatant| (combatant.entity.id() as usize == PLAYER) == (actor == PLAYER));
+
+    let fighter = &mut fighters[actor];
+    let (start, may_act) = begin_turn(fighter.entity, &fighter.stats, &mut fighter.effects);
+    let mut events: Vec<FightEvent> = start.into_iter().collect();
+
+    let mut fled = false;
+    match &fighter.enemy {
+        Some((enemy, ai)) => {
+            // Enemies act in the same frame, their turn start damage lands together with their action.
+            if may_act {
+                let me = Combatant { entity: fighter.entity, stats: fighter.stats };
+                match enemy_move(ai, &me, &mut fighter.effects, &allies, &foes, rng) {
+                    EnemyMove::Attack(target) => {
+                        let attack = fighter.effects.modify(&fighter.stats).attack;
+                        let status = enemy.enemy_type.roll_status(rng);
+                        events.push(attack_event(target, attack, enemy.attack_type, status));
+                    }
+                    EnemyMove::Heal(event) => events.push(event),
+                    EnemyMove::Flee(escaped) => fled = escaped,
+                    EnemyMove::Guard => (),
+                }
+            }
+            fighter.effects.on_turn_end();
+        }
+        None => {
+            // The party's menu only opens once the turn start damage has landed, and the fallen cannot choose.
+            land_events(config, fighters, events.drain(..), rng, report);
+            if fighters[actor].stats.health > 0 && may_act {
+                let (player, enemy) = (&fighters[PLAYER], &fighters[ENEMY]);
+                match choose_action(player, enemy, book, config.level) {
+                    CombatAction::Attack => {
+                        let attack = player.effects.modify(&player.stats).attack;
+                        events.push(attack_event(enemy.entity, attack, DamageType::Physical, None));
+                    }
+                    CombatAction::Skill(index) => {
+                        let skill = &book.0[index];
+                        events.extend(skill_events(skill, player.entity, enemy.entity, &[enemy.entity]));
+                        fighters[PLAYER].stats.spend_mana(skill.cost);
+                        report.skills += 1;
+                    }
+                    CombatAction::Wait => (),
+                }
+            }
+            fighters[actor].effects.on_turn_end();
+        }
+    }
+
+    if fled {
+        return Some(Outcome::Fled);
+    }
+    land_events(config, fighters, events.into_iter(), rng, report);
+
+    // Like `check_outcome`, the fight is lost first if both sides are down.
+    if fighters[PLAYER].stats.health == 0 {
+        Some(Outcome::Loss)
+    } else if fighters[ENEMY].stats.health == 0 {
+        Some(Outcome::Win)
+    } else {
+        None
+    }
+}
+
+/// Lands events in the order they were sent, like `damage_calculation`. Timed hits are
+/// left to chance in the game, here they land as if `timed_hits` was off.
+fn land_events(
+    config: &Config,
+    fighters: &mut [Fighter],
+    events: impl Iterator<Item = FightEvent>,
+    rng: &mut StdRng,
+    report: &mut Report,
+) {
+    for event in events {
+        let target = &mut fighters[event.target.id() as usize];
+        if target.stats.health == 0 {
+            continue;
+        }
+
+        let outcome = land_hit(
+            config.formula.as_ref(),
+            rng,
+            &event,
+            &mut target.stats,
+            &mut target.effects,
+            &target.affinities,
+        );
+        if event.piercing {
+            continue;
+        }
+        let distribution = match target.enemy {
+            Some(_) => &mut report.dealt,
+            None => &mut report.taken,
+        };
+        *distribution.entry(outcome.roll.damage).or_default() += 1;
+    }
+}
+
+/// Stands in for the player: heals when below half health, otherwise picks what should hurt the enemy most.
+fn choose_action(player: &Fighter, enemy: &Fighter, book: &SkillBook, level: usize) -> CombatAction {
+    let known: Vec<(usize, _)> = book.0
+        .iter()
+        .enumerate()
+        .filter(|(_, skill)| skill.level <= level && skill.cost <= player.stats.mana)
+        .collect();
+
+    if player.stats.health * 2 < player.stats.max_health {
+        if let Some((index, _)) = known.iter().find(|(_, skill)| skill.effect == SkillEffect::Heal) {
+            return CombatAction::Skill(*index);
+        }
+    }
+
+    let defense = enemy.effects.modify(&enemy.stats).defense;
+    let expected = |attack: isize, damage_type: DamageType| {
+        std::cmp::max(attack - defense, 1) as f32 * enemy.affinities.multiplier(damage_type)
+    };
+
+    let mut best = (
+        CombatAction::Attack,
+        expected(player.effects.modify(&player.stats).attack, DamageType::Physical),
+    );
+    for (index, skill) in known.iter().filter(|(_, skill)| skill.effect == SkillEffect::Damage) {
+        let damage = expected(skill.power, skill.damage_type);
+        if damage > best.1 {
+            best = (CombatAction::Skill(*index), damage);
+        }
+    }
+    best.0
+}
+
+fn print_report(enemy_type: EnemyType, config: &Config, report: &Report) {
+    let fights = config.fights as f32;
+
+    println!();
+    println!(
+        "{} ({}): won {:.1}%, lost {:.1}%, fled {:.1}%, {:.2} turns and {:.2} skills on average",
+        enemy_type.name(),
+        enemy_type.ai().0.name(),
+        report.wins as f32 / fights * 100.0,
+        report.losses as f32 / fights * 100.0,
+        report.fled as f32 / fights * 100.0,
+        report.turns as f32 / fights,
+        report.skills as f32 / fights,
+    );
+    print_distribution("  damage dealt", &report.dealt);
+    print_distribution("  damage taken", &report.taken);
+}
+
+fn print_distribution(label: &str, distribution: &BTreeMap<isize, usize>) {
+    let hits: usize = distribution.values().sum();
+    if hits == 0 {
+        println!("{}: none", label);
+        return;
+    }
+
+    let total: isize = distribution.iter().map(|(damage, count)| damage * *count as isize).sum();
+    println!("{}: {:.2} per hit on average", label, total as f32 / hits as f32);
+
+    for (damage, count) in distribution {
+        let share = *count as f32 / hits as f32;
+        println!("    {:>3} | {:<40} {:.1}%", damage, "#".repeat((share * 40.0).round() as usize), share * 100.0);
+    }
+}
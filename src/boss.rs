@@ -0,0 +1,250 @@
+use bevy::{prelude::*, sprite::collide_aabb::collide, math::const_vec3};
+
+use crate::{
+    GameState, TILESIZE, PLAYERSIZE,
+    ascii::{AsciiSheet, spawn_ascii_sprite},
+    combat::{CombatStats, Enemy, EnemyDefeated, EnemyType, spawn_combat_enemy},
+    combat_log::CombatLog,
+    element::DamageType,
+    fadeout::Fader,
+    player::Player,
+};
+
+const BOSSPOSITION: Vec3 = const_vec3!([0.0, 0.3, 40.0]);
+// Where summoned helpers appear, taken in turn.
+const SUMMONPOSITIONS: [Vec3; 2] = [const_vec3!([-0.9, 0.0, 40.0]), const_vec3!([0.9, 0.0, 40.0])];
+
+/// A boss waiting on the overworld. The map tile it was placed on identifies it in saves.
+#[derive(Component)]
+pub struct Boss {
+    pub enemy_type: EnemyType,
+    pub tile: (usize, usize),
+}
+
+#[derive(Clone, Copy)]
+pub struct BossFight {
+    pub enemy_type: EnemyType,
+    pub tile: (usize, usize),
+}
+
+/// The boss whose fight is starting or running, `None` in random encounters.
+#[derive(Default)]
+pub struct PendingBoss(pub Option<BossFight>);
+
+/// The tiles of every boss the player has beaten, they do not come back.
+#[derive(Default)]
+pub struct DefeatedBosses(pub Vec<(usize, usize)>);
+
+pub enum PhaseEffect {
+    SwitchAttack(DamageType),
+    Summon(EnemyType),
+}
+
+/// Kicks in once the boss drops below `below` of its max health.
+pub struct Phase {
+    pub below: f32,
+    pub message: &'static str,
+    pub effects: Vec<PhaseEffect>,
+}
+
+/// The phases a boss still has ahead of it, in order.
+#[derive(Component)]
+pub struct BossScript {
+    phases: Vec<Phase>,
+    next: usize,
+}
+
+/// The enemy a map symbol places as a boss.
+pub fn boss_for_symbol(symbol: char) -> Option<EnemyType> {
+    match symbol {
+        'K' => Some(EnemyType::RehuKing),
+        _ => None,
+    }
+}
+
+fn phases(enemy_type: EnemyType) -> Vec<Phase> {
+    match enemy_type {
+        EnemyType::RehuKing => vec![
+            Phase {
+                below: 0.5,
+                message: "The Rehu King bursts into flames!",
+                effects: vec![PhaseEffect::SwitchAttack(DamageType::Fire), PhaseEffect::Summon(EnemyType::Mibi)],
+            },
+            Phase {
+                below: 0.25,
+                message: "The Rehu King calls for help!",
+                effects: vec![PhaseEffect::Summon(EnemyType::Mibi)],
+            },
+        ],
+        _ => Vec::new(),
+    }
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingBoss>()
+            .init_resource::<DefeatedBosses>()
+            .add_system_set(SystemSet::on_enter(GameState::Overworld).with_system(show_bosses))
+            .add_system_set(SystemSet::on_update(GameState::Overworld).with_system(boss_touch))
+            .add_system_set(SystemSet::on_enter(GameState::Combat)
+                .with_system(hide_bosses)
+                .with_system(spawn_boss_enemy)
+            )
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(run_boss_scripts)
+                .with_system(record_boss_defeat)
+            );
+    }
+}
+
+/// Places a boss on the overworld, called by the map loader.
+pub fn spawn_boss(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    enemy_type: EnemyType,
+    tile: (usize, usize),
+    translation: Vec3,
+) -> Entity {
+    let boss = spawn_ascii_sprite(
+        commands,
+        ascii,
+        enemy_type.name().chars().next().unwrap_or('?') as usize,
+        Color::rgb(0.9, 0.2, 0.2),
+        translation,
+    );
+
+    commands
+        .entity(boss)
+        .insert(Name::new(format!("Boss - {}", enemy_type.name())))
+        .insert(Boss { enemy_type, tile })
+        .id()
+}
+
+fn show_bosses(
+    mut boss_query: Query<(&Boss, &mut Visibility)>,
+    defeated: Res<DefeatedBosses>,
+    mut pending: ResMut<PendingBoss>,
+) {
+    pending.0 = None;
+
+    // Loading an older save can bring a boss back.
+    for (boss, mut visibility) in boss_query.iter_mut() {
+        visibility.is_visible = !defeated.0.contains(&boss.tile);
+    }
+}
+
+fn hide_bosses(mut boss_query: Query<&mut Visibility, With<Boss>>) {
+    for mut visibility in boss_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+fn boss_touch(
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    boss_query: Query<(&Boss, &Transform), Without<Player>>,
+    defeated: Res<DefeatedBosses>,
+    mut pending: ResMut<PendingBoss>,
+    mut fader: Fader,
+) {
+    if fader.is_fading() {
+        return;
+    }
+
+    let player = player_query.single().translation;
+    for (boss, transform) in boss_query.iter() {
+        if defeated.0.contains(&boss.tile) {
+            continue;
+        }
+
+        let touching = collide(
+            player,
+            Vec2::splat(TILESIZE * PLAYERSIZE),
+            transform.translation,
+            Vec2::splat(TILESIZE),
+        );
+        if touching.is_some() {
+            pending.0 = Some(BossFight {
+                enemy_type: boss.enemy_type,
+                tile: boss.tile,
+            });
+            fader.fade_to(&mut commands, GameState::Combat);
+            return;
+        }
+    }
+}
+
+fn spawn_boss_enemy(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ascii: Res<AsciiSheet>,
+    pending: Res<PendingBoss>,
+    mut log: ResMut<CombatLog>,
+) {
+    let fight = match pending.0 {
+        Some(fight) => fight,
+        None => return,
+    };
+
+    let boss = spawn_combat_enemy(&mut commands, &asset_server, &ascii, fight.enemy_type, BOSSPOSITION);
+    commands.entity(boss).insert(BossScript {
+        phases: phases(fight.enemy_type),
+        next: 0,
+    });
+
+    log.push(format!("The {} blocks your way!", fight.enemy_type.name()));
+}
+
+fn run_boss_scripts(
+    mut commands: Commands,
+    mut boss_query: Query<(&mut BossScript, &mut Enemy, &CombatStats)>,
+    enemy_query: Query<(), With<Enemy>>,
+    asset_server: Res<AssetServer>,
+    ascii: Res<AsciiSheet>,
+    mut log: ResMut<CombatLog>,
+) {
+    let mut summoned = enemy_query.iter().count().saturating_sub(1);
+
+    for (mut script, mut enemy, stats) in boss_query.iter_mut() {
+        if stats.health == 0 {
+            continue;
+        }
+
+        // A big hit can skip past several phases at once, they all still happen.
+        while let Some(phase) = script.phases.get(script.next) {
+            if stats.health as f32 >= stats.max_health as f32 * phase.below {
+                break;
+            }
+
+            log.push(phase.message);
+            for effect in phase.effects.iter() {
+                match effect {
+                    PhaseEffect::SwitchAttack(damage_type) => enemy.attack_type = *damage_type,
+                    PhaseEffect::Summon(enemy_type) => {
+                        let position = SUMMONPOSITIONS[summoned % SUMMONPOSITIONS.len()];
+                        spawn_combat_enemy(&mut commands, &asset_server, &ascii, *enemy_type, position);
+                        log.push(format!("A {} joins the fight!", enemy_type.name()));
+                        summoned += 1;
+                    }
+                }
+            }
+            script.next += 1;
+        }
+    }
+}
+
+fn record_boss_defeat(
+    mut defeated_event: EventReader<EnemyDefeated>,
+    pending: Res<PendingBoss>,
+    mut defeated: ResMut<DefeatedBosses>,
+) {
+    for event in defeated_event.iter() {
+        if let Some(fight) = pending.0 {
+            if event.0 == fight.enemy_type && !defeated.0.contains(&fight.tile) {
+                defeated.0.push(fight.tile);
+            }
+        }
+    }
+}
@@ -1,193 +1,1261 @@
 #![allow(unused_imports)]
 
-use bevy::prelude::*;
-use rand::Rng;
+use std::collections::VecDeque;
 
-use crate::{GameState,
+use bevy::{prelude::*, ecs::{query::WorldQuery, system::SystemParam}};
+use rand::{Rng, rngs::StdRng};
+
+use crate::{GameState, TILESIZE,
         ascii::{AsciiSheet, spawn_ascii_sprite}, 
-        fadeout::{create_fadeout, FadeoutTimer}, 
+        fadeout::{create_fadeout, Fader, FadeoutTimer}, 
+        healthbar::{HealthBar, PartyPanel, spawn_health_bar},
+        popup::{PopupKind, spawn_popup, spawn_popup_text},
+        experience::ExperienceEvent,
+        status::{StatusEffects, StatusKind},
+        skills::{KnownSkills, Skill, SkillBook, SkillEffect, SkillTarget},
+        combat_menu::{CombatAction, CombatMenu},
+        element::{Affinities, DamageType, Effectiveness},
+        damage::{CombatRng, DamageFormula, DamageModel, DamageRoll, resolve_hit},
+        combat_log::CombatLog,
+        settings::{CombatMode, Settings},
+        ai::{Combatant, EnemyAction, EnemyAi, behavior},
+        boss::PendingBoss,
+        animation::{AnimationKind, CombatAnimation, play_animation},
+        timing::{ActionCommand, TimingKind, spawn_prompt},
+        grid::{BattleGrid, GridPosition, GridTurn},
+        actions::{Action, Actions},
         player::{Player, self}
     };
 
-#[derive(Component)]
-struct Enemy;
+const ENEMYBAROFFSET: f32 = 0.7; // How far above the enemy its health bar is drawn.
+const POPUPOFFSET: f32 = 0.3;
+pub const FLEECHANCE: f64 = 0.5;
 
-struct FightEvent {
-    target: Entity,
-    damage_amount: isize,
+#[derive(Component)]
+pub struct Enemy {
+    pub enemy_type: EnemyType,
+    pub attack_type: DamageType, // Starts as the type's own, boss phases may switch it.
 }
 
+/// Everybody taking part in the fight, the party and the enemies.
+type InFight = Or<(With<PartyMember>, With<Enemy>)>;
+/// The enemy side only, disjoint from any query on the party.
+type EnemySide = (With<Enemy>, Without<PartyMember>);
+
+/// An enemy that ran away, it counts as out of the fight but gives no rewards.
 #[derive(Component)]
+pub struct Fled;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyType {
+    Rehu,
+    Imi,
+    Mibi,
+    RehuKing,
+}
+
+impl EnemyType {
+    /// The enemies random encounters pick from.
+    pub const ALL: [EnemyType; 3] = [EnemyType::Rehu, EnemyType::Imi, EnemyType::Mibi];
+    /// The enemies only met as placed bosses.
+    pub const BOSSES: [EnemyType; 1] = [EnemyType::RehuKing];
+
+    pub fn parse(name: &str) -> Option<EnemyType> {
+        EnemyType::ALL
+            .into_iter()
+            .chain(EnemyType::BOSSES)
+            .find(|enemy_type| enemy_type.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyType::Rehu => "Rehu",
+            EnemyType::Imi => "Imi",
+            EnemyType::Mibi => "Mibi",
+            EnemyType::RehuKing => "Rehu King",
+        }
+    }
+
+    fn texture(&self) -> &'static str {
+        match self {
+            EnemyType::Rehu | EnemyType::RehuKing => "rehu.png",
+            EnemyType::Imi => "imi.png",
+            EnemyType::Mibi => "mibi.png",
+        }
+    }
+
+    fn scale(&self) -> f32 {
+        match self {
+            EnemyType::RehuKing => 0.0015,
+            _ => 0.001,
+        }
+    }
+
+    pub fn stats(&self) -> CombatStats {
+        let (health, max_health, speed) = match self {
+            EnemyType::Rehu => (5, 5, 3),
+            EnemyType::Imi => (3, 3, 5),
+            EnemyType::Mibi => (3, 3, 7),
+            EnemyType::RehuKing => (20, 20, 4),
+        };
+        let (attack, defense) = match self {
+            EnemyType::RehuKing => (3, 2),
+            _ => (2, 1),
+        };
+
+        CombatStats {
+            health,
+            max_health,
+            attack,
+            defense,
+            mana: 0,
+            max_mana: 0,
+            speed,
+        }
+    }
+
+    /// The element of this enemy's basic attack.
+    pub fn attack_type(&self) -> DamageType {
+        match self {
+            EnemyType::Rehu | EnemyType::RehuKing => DamageType::Earth,
+            EnemyType::Imi => DamageType::Fire,
+            EnemyType::Mibi => DamageType::Water,
+        }
+    }
+
+    /// How much of each damage type this enemy takes, this is what sets them apart in a fight.
+    pub fn affinities(&self) -> Affinities {
+        Affinities(match self {
+            EnemyType::Rehu => vec![(DamageType::Fire, 2.0), (DamageType::Physical, 0.5)],
+            EnemyType::Imi => vec![(DamageType::Water, 2.0), (DamageType::Earth, 0.0)],
+            EnemyType::Mibi => vec![(DamageType::Air, 2.0), (DamageType::Fire, 0.5)],
+            EnemyType::RehuKing => vec![(DamageType::Fire, 1.5), (DamageType::Physical, 0.75)],
+        })
+    }
+
+    /// The status this enemy's attacks may inflict, with the chance to do so.
+    pub fn inflicts(&self) -> Option<(StatusKind, f64)> {
+        match self {
+            EnemyType::Rehu | EnemyType::RehuKing => Some((StatusKind::Stun, 0.2)),
+            EnemyType::Imi => Some((StatusKind::Sleep, 0.2)),
+            EnemyType::Mibi => Some((StatusKind::Poison, 0.3)),
+        }
+    }
+
+    /// Rolls whether this enemy's next attack inflicts its status.
+    pub fn roll_status(&self, rng: &mut StdRng) -> Option<StatusKind> {
+        self.inflicts()
+            .filter(|(_, chance)| rng.gen_bool(*chance))
+            .map(|(status, _)| status)
+    }
+
+    /// The name of the behavior this enemy decides its turns with, see `ai::behavior`.
+    pub fn behavior(&self) -> &'static str {
+        match self {
+            EnemyType::Rehu => "defensive",
+            EnemyType::Imi => "coward",
+            EnemyType::Mibi => "healer",
+            EnemyType::RehuKing => "aggressive",
+        }
+    }
+
+    pub fn ai(&self) -> EnemyAi {
+        EnemyAi(behavior(self.behavior()).expect("Enemy with an unknown behavior!"))
+    }
+
+    /// The chance that this enemy offers to join the party once defeated.
+    pub fn recruit_chance(&self) -> f64 {
+        match self {
+            EnemyType::Rehu => 0.25,
+            EnemyType::Imi => 0.25,
+            EnemyType::Mibi => 0.4,
+            EnemyType::RehuKing => 0.0,
+        }
+    }
+
+    /// The experience the player gets for defeating this enemy.
+    pub fn experience(&self) -> usize {
+        match self {
+            EnemyType::Rehu => 5,
+            EnemyType::Imi => 3,
+            EnemyType::Mibi => 2,
+            EnemyType::RehuKing => 20,
+        }
+    }
+}
+
+/// A hit, heal or status on one target, before its defense and affinities.
+#[derive(Clone, Copy)]
+pub struct FightEvent {
+    pub target: Entity,
+    pub damage_amount: isize,
+    pub damage_type: DamageType,
+    pub piercing: bool, // Ignores the target's defense and affinities, e.g. poison.
+    pub status: Option<StatusKind>,
+    pub timed: bool, // Waits for an action command first when timed hits are on.
+}
+
+/// What a `FightEvent` did once it landed.
+pub struct HitOutcome {
+    pub roll: DamageRoll,
+    pub effectiveness: Effectiveness,
+    pub woke_up: bool,
+    pub status: Option<StatusKind>, // Only set if the status took hold.
+}
+
+/// Timed hits waiting for their press, only the first one's prompt is on screen.
+/// A hit leaves the queue once its press was judged, so one press never decides several hits.
+#[derive(Default)]
+struct HeldHits(VecDeque<FightEvent>);
+
+/// Animations and timed hits that are still playing out, nobody acts before they are done.
+#[derive(SystemParam)]
+struct Playing<'w, 's> {
+    animation_query: Query<'w, 's, (), With<CombatAnimation>>,
+    held: Res<'w, HeldHits>,
+}
+
+impl Playing<'_, '_> {
+    fn is_done(&self) -> bool {
+        self.animation_query.is_empty() && self.held.0.is_empty()
+    }
+}
+
+/// Sent when an enemy's health hits 0, so its loot can be rolled.
+pub struct EnemyDefeated(pub EnemyType);
+
+#[derive(Component, Clone, Copy)]
 pub struct CombatStats {
     pub health: isize,
     pub max_health: isize,
     pub attack: isize,
     pub defense: isize,
+    pub mana: isize,
+    pub max_mana: isize,
+    pub speed: isize,
+}
+
+impl CombatStats {
+    /// Negative damage heals, health always stays between 0 and `max_health`.
+    pub fn take_damage(&mut self, damage: isize) {
+        if damage < 0 {
+            self.heal(-damage);
+            return;
+        }
+        self.health = (self.health - damage).clamp(0, self.max_health);
+    }
+
+    /// All healing goes through here, so nobody ends up above `max_health`.
+    /// Returns how much health was actually restored.
+    pub fn heal(&mut self, amount: isize) -> isize {
+        let before = self.health;
+        self.health = (self.health + amount.max(0)).clamp(0, self.max_health);
+        self.health - before
+    }
+
+    /// Pays `cost` mana, returns false and pays nothing if there is not enough left.
+    pub fn spend_mana(&mut self, cost: isize) -> bool {
+        if cost > self.mana {
+            return false;
+        }
+        self.mana -= cost;
+        true
+    }
 }
 
 struct CombatCooldown(Timer);
 
+const ATBRATE: f32 = 0.06; // Gauge filled per point of speed and second.
+const TIMELINELENGTH: usize = 6;
+
+/// How close a combatant is to acting in an active time battle, full at 1.0.
+#[derive(Component, Default)]
+pub struct AtbGauge(pub f32);
+
+impl AtbGauge {
+    /// Fills the gauge for `delta` seconds at the given speed, returns whether it is full.
+    pub fn fill(&mut self, speed: isize, delta: f32) -> bool {
+        self.0 = (self.0 + speed as f32 * ATBRATE * delta).min(1.0);
+        self.0 >= 1.0
+    }
+}
+
+/// Fights on the player's side, the player itself and every active colleague.
+#[derive(Component)]
+pub struct PartyMember;
+
+/// Whose move it is. Turn based fights step through the `TurnOrder` while `Waiting`,
+/// active time battles wait for the next full gauge.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CombatTurn {
+    Party(Entity), // This party member's action menu is open.
+    Waiting,
+    Over, // One side is down, nobody acts anymore.
+}
+
+/// Who still acts this round in turn based fights, fastest first.
+#[derive(Default)]
+struct TurnOrder(VecDeque<Entity>);
+
+/// The next few combatants to act, the one acting right now first.
+#[derive(Default)]
+pub struct Timeline(pub Vec<Entity>);
+
 pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CombatCooldown(Timer::from_seconds(0.5, false)))
+            .insert_resource(CombatTurn::Waiting)
+            .init_resource::<TurnOrder>()
+            .init_resource::<Timeline>()
+            .init_resource::<HeldHits>()
             .add_event::<FightEvent>()
+            .add_event::<EnemyDefeated>()
             .add_system_set(SystemSet::on_update(GameState::Combat)
                 .with_system(damage_calculation)
-                .with_system(combat_input)
+                .with_system(hold_timed_hits.after(land_timed_hits))
+                .with_system(land_timed_hits.after("action_commands"))
+                .with_system(combat_input.after("combat_log").after("grid_movement"))
+                .with_system(take_turns)
+                .with_system(fill_atb_gauges)
+                .with_system(check_outcome.after(damage_calculation))
+                .with_system(update_timeline)
+                .with_system(hide_fled_enemies)
                 //.with_system(test_exit_combat)
                 .with_system(combat_camera)
             )
-            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(spawn_enemy))
+            .add_system_set(SystemSet::on_enter(GameState::Combat)
+                .with_system(spawn_enemy)
+                .with_system(start_combat)
+            )
             .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_enemies));
     }
 }
 
+/// Whatever a hit can land on.
+type HitTarget<'w, 's> = Query<'w, 's, (
+    &'static mut Name,
+    &'static mut CombatStats,
+    &'static mut StatusEffects,
+    &'static Affinities,
+    &'static Transform,
+    Option<&'static PartyMember>,
+    Option<&'static Enemy>,
+)>;
+
+/// Everything that shows a landed hit: popups, animations and the log.
+#[derive(SystemParam)]
+struct HitFeedback<'w, 's> {
+    commands: Commands<'w, 's>,
+    ascii: Res<'w, AsciiSheet>,
+    panel_query: Query<'w, 's, (Entity, &'static HealthBar, &'static Transform), With<PartyPanel>>,
+    log: ResMut<'w, CombatLog>,
+}
+
+/// Tells the experience and loot systems about a defeated enemy.
+#[derive(SystemParam)]
+struct Defeats<'w, 's> {
+    experience_event: EventWriter<'w, 's, ExperienceEvent>,
+    defeated_event: EventWriter<'w, 's, EnemyDefeated>,
+}
+
+impl Defeats<'_, '_> {
+    fn send(&mut self, enemy_type: EnemyType) {
+        self.experience_event.send(ExperienceEvent(enemy_type.experience()));
+        self.defeated_event.send(EnemyDefeated(enemy_type));
+    }
+}
+
 fn damage_calculation(
-    mut commands: Commands,
     mut fight_event: EventReader<FightEvent>,
-    mut target_query: Query<(&mut Name, &mut CombatStats)>,
-    ascii: Res<AsciiSheet>,
-    mut fade_timer: ResMut<FadeoutTimer>,
+    mut target_query: HitTarget,
+    feedback: HitFeedback,
+    mut defeats: Defeats,
+    model: Res<DamageModel>,
+    mut rng: ResMut<CombatRng>,
+    settings: Res<Settings>,
 ) {
+    let HitFeedback { mut commands, ascii, panel_query, mut log } = feedback;
+
     for event in fight_event.iter() {
-        let (name, mut target_stats): (Mut<'_, Name>, Mut<'_, CombatStats>) = target_query
+        // These come back through `land_timed_hits` once the press was judged.
+        if settings.timed_hits && event.timed {
+            continue;
+        }
+
+        let (name, mut target_stats, mut effects, affinities, transform, member, enemy) = target_query
             .get_mut(event.target)
             .expect("Fighting target without stats!");
-        
-        target_stats.health = std::cmp::max(target_stats.health - (event.damage_amount-target_stats.defense), 0);
-        println!("{} has {} hp left", name.to_owned(), target_stats.health);
+
+        // Hits that land while the fight is already fading out.
+        if target_stats.health == 0 {
+            continue;
+        }
+
+        let outcome = land_hit(model.0.as_ref(), &mut rng.0, event, &mut target_stats, &mut effects, affinities);
+        let (roll, effectiveness) = (outcome.roll, outcome.effectiveness);
+        let damage = roll.damage;
+        log.push(format!("{} has {} hp left", name.as_str(), target_stats.health));
+
+        if outcome.woke_up {
+            log.push(format!("{} wakes up!", name.as_str()));
+        }
+        if let Some(status) = outcome.status {
+            log.push(format!("{} is {}!", name.as_str(), status.name()));
+        }
+
+        // The party is hidden during combat, so its numbers pop up over the status panels.
+        let panel = panel_query.iter().find(|(_, bar, _)| bar.owner() == event.target);
+        let (visual, position) = match (member, panel) {
+            (Some(_), Some((panel, _, panel_transform))) => {
+                (panel, panel_transform.translation + Vec3::new(0.0, POPUPOFFSET, 100.0))
+            }
+            _ => (event.target, transform.translation + Vec3::new(0.0, POPUPOFFSET, 900.0)),
+        };
+        if let (Some(message), Some(popup)) = (effectiveness.message(), effectiveness.popup()) {
+            log.push(message);
+            spawn_popup_text(
+                &mut commands,
+                &ascii,
+                popup,
+                effectiveness.color(),
+                position + Vec3::new(0.0, TILESIZE, 0.0),
+            );
+        }
+
+        let kind = if !roll.hit {
+            PopupKind::Miss
+        } else if damage < 0 {
+            PopupKind::Heal
+        } else if roll.critical {
+            PopupKind::Critical
+        } else {
+            PopupKind::Damage
+        };
+        if roll.critical {
+            log.push("A critical hit!");
+        }
+        // Pure status effects and immune targets have no number to show.
+        if !(event.piercing && damage == 0) && effectiveness != Effectiveness::Immune {
+            spawn_popup(&mut commands, &ascii, kind, damage.abs(), position);
+        }
+
+        if target_stats.health == 0 && enemy.is_some() {
+            play_animation(&mut commands, visual, AnimationKind::FadeOut);
+        } else if roll.hit && damage > 0 {
+            play_animation(&mut commands, visual, AnimationKind::Shake);
+        }
 
         if target_stats.health == 0 {
-            println!("{} has died.", name.to_owned());
-            create_fadeout(&mut commands, GameState::Overworld, &ascii, &mut fade_timer);
+            log.push(format!("{} has died.", name.as_str()));
+            if let Some(enemy) = enemy {
+                defeats.send(enemy.enemy_type);
+            }
         }
     }
 }
 
-fn combat_input(keyboard: ResMut<Input<KeyCode>>,
+/// Holds back timed hits behind a prompt, hits on the party are blocked, all others are strikes.
+/// Puts up the prompt for a timed hit, labelled with the key bound to confirm.
+#[derive(SystemParam)]
+struct Prompter<'w, 's> {
+    commands: Commands<'w, 's>,
+    prompt_query: Query<'w, 's, (), With<ActionCommand>>,
+    ascii: Res<'w, AsciiSheet>,
+    actions: Res<'w, Actions>,
+}
+
+impl Prompter<'_, '_> {
+    fn is_prompting(&self) -> bool {
+        !self.prompt_query.is_empty()
+    }
+
+    fn prompt(&mut self, command: ActionCommand) {
+        spawn_prompt(&mut self.commands, &self.ascii, &self.actions.key_name(Action::Confirm), command);
+    }
+}
+
+fn hold_timed_hits(
+    mut fight_event: EventReader<FightEvent>,
+    party_query: Query<(), With<PartyMember>>,
+    mut prompter: Prompter,
+    mut held: ResMut<HeldHits>,
+    settings: Res<Settings>,
+) {
+    for event in fight_event.iter() {
+        if settings.timed_hits && event.timed {
+            held.0.push_back(*event);
+        }
+    }
+
+    // The next prompt comes up once the last one is gone.
+    let next = match held.0.front() {
+        Some(next) if !prompter.is_prompting() => next,
+        _ => return,
+    };
+    let command = match party_query.get(next.target) {
+        Ok(_) => ActionCommand::new(TimingKind::Defense, settings.defense_window),
+        Err(_) => ActionCommand::new(TimingKind::Attack, settings.attack_window),
+    };
+    prompter.prompt(command);
+}
+
+fn land_timed_hits(
+    mut commands: Commands,
     mut fight_event: EventWriter<FightEvent>,
-    player_query: Query<&CombatStats, With<Player>>,
-    enemy_query: Query<Entity, With<Enemy>>,
-    time: Res<Time>,
+    prompt_query: Query<(Entity, &ActionCommand)>,
+    mut held: ResMut<HeldHits>,
+    mut log: ResMut<CombatLog>,
+) {
+    let (prompt, command) = match prompt_query.get_single() {
+        Ok(prompt) => prompt,
+        Err(_) => return,
+    };
+    let success = match command.verdict() {
+        Some(success) => success,
+        None => return,
+    };
+    let hit = match held.0.pop_front() {
+        Some(hit) => hit,
+        None => return,
+    };
+
+    if success {
+        log.push(match command.kind {
+            TimingKind::Attack => "A well timed strike!",
+            TimingKind::Defense => "A well timed block!",
+        });
+    }
+
+    fight_event.send(FightEvent {
+        damage_amount: command.timed_damage(hit.damage_amount),
+        timed: false,
+        ..hit
+    });
+    commands.entity(prompt).despawn_recursive();
+}
+
+fn check_outcome(
+    mut commands: Commands,
+    mut party_query: Query<(&Name, &mut CombatStats), With<PartyMember>>,
+    enemy_query: Query<(&CombatStats, Option<&Fled>), EnemySide>,
+    mut turn: ResMut<CombatTurn>,
+    mut log: ResMut<CombatLog>,
+    mut fader: Fader,
+) {
+    if *turn == CombatTurn::Over || enemy_query.is_empty() || party_query.is_empty() {
+        return;
+    }
+
+    // The fight is only lost once nobody in the party is left standing.
+    if party_query.iter().all(|(_, stats)| stats.health == 0) {
+        log.push("Your party has fallen...");
+        fader.fade_to(&mut commands, GameState::GameOver);
+        *turn = CombatTurn::Over;
+        return;
+    }
+
+    if enemy_query.iter().all(|(stats, _)| stats.health == 0) {
+        if enemy_query.iter().all(|(_, fled)| fled.is_some()) {
+            log.push("The enemies got away.");
+        } else {
+            log.push("You won the fight!");
+        }
+
+        for (name, mut stats) in party_query.iter_mut() {
+            if stats.health == 0 {
+                stats.heal(1);
+                log.push(format!("{} gets back up.", name.as_str()));
+            }
+        }
+        *turn = CombatTurn::Over;
+    }
+}
+
+fn hide_fled_enemies(mut enemy_query: Query<&mut Visibility, Added<Fled>>) {
+    for mut visibility in enemy_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+fn start_combat(
+    mut turn: ResMut<CombatTurn>,
+    mut order: ResMut<TurnOrder>,
+    mut held: ResMut<HeldHits>,
     mut combat_timer: ResMut<CombatCooldown>,
+    mut gauge_query: Query<&mut AtbGauge>,
 ) {
-    combat_timer.0.tick(time.delta());
+    *turn = CombatTurn::Waiting;
+    order.0.clear();
+    held.0.clear();
+    combat_timer.0.reset();
 
-    let target = enemy_query.single();
-    let player_stats = player_query.single();
+    for mut gauge in gauge_query.iter_mut() {
+        gauge.0 = 0.0;
+    }
+}
 
-    if keyboard.just_pressed(KeyCode::Return) && combat_timer.0.finished() {
-        fight_event.send(FightEvent {
-            target,
-            damage_amount: player_stats.attack,
-        });
-        combat_timer.0.reset();
+/// The party member choosing what to do.
+type Chooser<'w, 's> = Query<'w, 's, (
+    &'static Name,
+    &'static mut CombatStats,
+    &'static mut StatusEffects,
+    &'static mut AtbGauge,
+    &'static KnownSkills,
+), With<PartyMember>>;
+
+/// The enemies a party member can pick from, and where everybody stands on the battle grid.
+#[derive(SystemParam)]
+struct Targets<'w, 's> {
+    enemy_query: Query<'w, 's, (Entity, &'static CombatStats), EnemySide>,
+    position_query: Query<'w, 's, &'static GridPosition>,
+}
+
+impl Targets<'_, '_> {
+    fn living(&self) -> Vec<Entity> {
+        self.enemy_query
+            .iter()
+            .filter(|(_, stats)| stats.health > 0)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// The first of `enemies` the member can hit, on the battle grid only neighbours are in reach.
+    fn in_reach(&self, member: Entity, enemies: &[Entity]) -> Option<Entity> {
+        let member_position = self.position_query.get(member).ok();
+        enemies.iter().copied().find(|enemy| match (member_position, self.position_query.get(*enemy)) {
+            (Some(member_position), Ok(enemy_position)) => member_position.is_adjacent(enemy_position),
+            _ => true,
+        })
     }
 }
 
-fn combat_camera(mut camera_query: Query<&mut Transform, With<Camera>>) {
-    let mut camera_transform = camera_query.single_mut();
-    camera_transform.translation.x = 0.0;
-    camera_transform.translation.y = 0.0;
+/// The action highlighted in the combat menu, taken once it is confirmed.
+#[derive(SystemParam)]
+struct MenuChoice<'w, 's> {
+    actions: Res<'w, Actions>,
+    menu: Res<'w, CombatMenu>,
+    book: Res<'w, SkillBook>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
 }
 
-fn spawn_enemy(commands: Commands, asset_server: Res<AssetServer>) {
-    const CHOICES: usize = 3;
+impl MenuChoice<'_, '_> {
+    fn confirmed(&self, known: &KnownSkills) -> Option<CombatAction> {
+        match self.actions.just_pressed(Action::Confirm) {
+            true => Some(self.menu.action(known)),
+            false => None,
+        }
+    }
+}
 
-    let choice = rand::thread_rng().gen_range(0..CHOICES);
+/// What a party member's action puts out: the hits, its panel's lunge and the log lines.
+#[derive(SystemParam)]
+struct PartyAct<'w, 's> {
+    commands: Commands<'w, 's>,
+    fight_event: EventWriter<'w, 's, FightEvent>,
+    panel_query: Query<'w, 's, (Entity, &'static HealthBar), With<PartyPanel>>,
+    log: ResMut<'w, CombatLog>,
+}
 
-    let (name, hp): (&str, isize) = match choice {
-        0 => { spawn_rehu(commands, asset_server) },
-        1 => { spawn_imi(commands, asset_server) },
-        _ => { spawn_mibi(commands, asset_server)}
+impl PartyAct<'_, '_> {
+    /// The member's panel lunges up towards the enemies.
+    fn lunge(&mut self, member: Entity) {
+        if let Some((panel, _)) = self.panel_query.iter().find(|(_, bar)| bar.owner() == member) {
+            play_animation(&mut self.commands, panel, AnimationKind::Lunge(Vec3::Y));
+        }
+    }
+}
+
+/// What a party member waits for before acting: animations, timed hits, a grid walk and the cooldown.
+#[derive(SystemParam)]
+struct Pacing<'w, 's> {
+    playing: Playing<'w, 's>,
+    grid_turn: Res<'w, GridTurn>,
+    settings: Res<'w, Settings>,
+    time: Res<'w, Time>,
+    combat_timer: ResMut<'w, CombatCooldown>,
+}
+
+impl Pacing<'_, '_> {
+    fn tick(&mut self) {
+        self.combat_timer.0.tick(self.time.delta());
+    }
+
+    fn is_ready(&self) -> bool {
+        // Active time battles are paced by the gauges instead of the cooldown.
+        let cooled_down = self.settings.combat_mode == CombatMode::Atb || self.combat_timer.0.finished();
+        self.playing.is_done() && !self.grid_turn.moving && cooled_down
+    }
+
+    fn reset(&mut self) {
+        self.combat_timer.0.reset();
+    }
+}
+
+fn combat_input(
+    mut party_query: Chooser,
+    targets: Targets,
+    choice: MenuChoice,
+    mut act: PartyAct,
+    mut pacing: Pacing,
+    mut turn: ResMut<CombatTurn>,
+) {
+    pacing.tick();
+
+    let member = match *turn {
+        CombatTurn::Party(member) => member,
+        _ => return,
     };
-    
-        println!("A wild {} appears! It has {} hp.", name, hp);
+    let (name, mut stats, mut effects, mut gauge, known) = match party_query.get_mut(member) {
+        Ok(components) => components,
+        Err(_) => {
+            *turn = CombatTurn::Waiting;
+            return;
+        }
+    };
+
+    // Knocked out while choosing, the turn is lost.
+    if stats.health == 0 {
+        gauge.0 = 0.0;
+        *turn = CombatTurn::Waiting;
+        return;
+    }
+
+    if !act.log.is_idle() || !pacing.is_ready() {
+        return;
+    }
+    let action = match choice.confirmed(known) {
+        Some(action) => action,
+        None => return,
+    };
+
+    let living_enemies = targets.living();
+    let target = match living_enemies.first() {
+        Some(&target) => target,
+        None => return,
+    };
+
+    match action {
+        CombatAction::Attack => {
+            // On the battle grid only neighbours can be hit, skills reach everywhere.
+            let target = match targets.in_reach(member, &living_enemies) {
+                Some(target) => target,
+                None => {
+                    act.log.push("Nobody is in reach!");
+                    return;
+                }
+            };
+
+            act.fight_event.send(attack_event(target, effects.modify(&stats).attack, DamageType::Physical, None));
+        }
+        CombatAction::Skill(index) => {
+            let skill = &choice.book.0[index];
+            if !stats.spend_mana(skill.cost) {
+                act.log.push(format!("Not enough MP for {}!", skill.name));
+                return;
+            }
+            act.log.push(format!("{} uses {}!", name.as_str(), skill.name));
+
+            for event in skill_events(skill, member, target, &living_enemies) {
+                act.fight_event.send(event);
+            }
+        }
+        CombatAction::Wait => act.log.push(format!("{} waits.", name.as_str())),
+    }
+
+    if action != CombatAction::Wait {
+        act.lunge(member);
+    }
+    effects.on_turn_end();
+    gauge.0 = 0.0;
+    pacing.reset();
+    *turn = CombatTurn::Waiting;
 }
 
-fn spawn_mibi<'a>(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) -> (&'a str, isize) {
-    let name = "Mibi";
-    let hp = 1;
+/// Everyone whose turn can come up.
+#[derive(WorldQuery)]
+#[world_query(mutable)]
+struct TurnTaker<'w> {
+    entity: Entity,
+    name: &'w Name,
+    stats: &'w mut CombatStats,
+    effects: &'w mut StatusEffects,
+    gauge: &'w mut AtbGauge,
+    enemy: Option<&'w Enemy>,
+    ai: Option<&'w EnemyAi>,
+}
 
-    commands.spawn_bundle(SpriteBundle {
-            texture: asset_server.load("mibi.png"),
-            transform: Transform {
-                translation: Vec3::new(0.0, 0.2, 0.0),
-                scale: Vec3::new(0.001, 0.001, 1.0),
-                ..default()
-            },
-            ..default()
-        })
-        .insert(Enemy)
-        .insert(Name::new(name))
-        .insert(CombatStats {
-            health: hp,
-            max_health: 3,
-            attack: 2,
-            defense: 1,
-        });
-    (name, hp)
+/// What it takes to play out a turn: the hits, the log, the dice and the battle grid.
+#[derive(SystemParam)]
+struct TurnContext<'w, 's> {
+    commands: Commands<'w, 's>,
+    fight_event: EventWriter<'w, 's, FightEvent>,
+    rng: ResMut<'w, CombatRng>,
+    log: ResMut<'w, CombatLog>,
+    position_query: Query<'w, 's, &'static mut GridPosition>,
+    grid: Option<Res<'w, BattleGrid>>,
 }
 
-fn spawn_imi<'a>(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) -> (&'a str, isize) {
-    let name = "Imi";
-    let hp = 3;
+impl TurnContext<'_, '_> {
+    /// Runs the turn start hooks of the active effects, returns whether the combatant may act.
+    fn start_turn(&mut self, taker: &mut TurnTakerItem) -> bool {
+        let (damage, may_act) = begin_turn(taker.entity, &taker.stats, &mut taker.effects);
 
-    commands.spawn_bundle(SpriteBundle {
-            texture: asset_server.load("imi.png"),
-            transform: Transform {
-                translation: Vec3::new(0.0, 0.2, 0.0),
-                scale: Vec3::new(0.001, 0.001, 1.0),
-                ..default()
+        if let Some(damage) = damage {
+            self.fight_event.send(damage);
+        }
+        if !may_act {
+            self.log.push(format!("{} cannot move!", taker.name.as_str()));
+        }
+
+        may_act
+    }
+
+    /// Moves next to the target on the battle grid, returns whether it got there.
+    fn approach(&mut self, mover: Entity, target: Entity) -> bool {
+        match &self.grid {
+            Some(grid) => grid.approach(&mut self.position_query, mover, target),
+            None => true,
+        }
+    }
+
+    /// Lets the enemy's behavior pick an action and carries it out.
+    fn enemy_act(&mut self, taker: &mut TurnTakerItem, enemy: &Enemy, ai: &EnemyAi, allies: &[Combatant], foes: &[Combatant]) {
+        let entity = taker.entity;
+        let name = taker.name.as_str();
+        let me = Combatant { entity, stats: *taker.stats };
+
+        match enemy_move(ai, &me, &mut taker.effects, allies, foes, &mut self.rng.0) {
+            EnemyMove::Attack(target) => {
+                if !self.approach(entity, target) {
+                    self.log.push(format!("{} moves closer.", name));
+                    return;
+                }
+
+                // Enemies stand above the player's panel, so they lunge down.
+                play_animation(&mut self.commands, entity, AnimationKind::Lunge(-Vec3::Y));
+
+                let status = enemy.enemy_type.roll_status(&mut self.rng.0);
+                let attack = taker.effects.modify(&taker.stats).attack;
+                self.fight_event.send(attack_event(target, attack, enemy.attack_type, status));
+            }
+            EnemyMove::Guard => self.log.push(format!("{} is {}!", name, StatusKind::Guard.name())),
+            EnemyMove::Heal(event) => {
+                self.log.push(format!("{} casts a healing spell!", name));
+                self.fight_event.send(event);
+            }
+            EnemyMove::Flee(true) => {
+                self.log.push(format!("{} runs away!", name));
+                taker.stats.health = 0;
+                self.commands.entity(entity).insert(Fled);
+            }
+            EnemyMove::Flee(false) => self.log.push(format!("{} tries to run, but cannot get away!", name)),
+        }
+    }
+}
+
+/// Hands out the turns of a turn based fight, one combatant per cooldown.
+fn take_turns(
+    mut combatant_query: Query<TurnTaker, InFight>,
+    mut context: TurnContext,
+    mut order: ResMut<TurnOrder>,
+    mut combat_timer: ResMut<CombatCooldown>,
+    mut turn: ResMut<CombatTurn>,
+    playing: Playing,
+    settings: Res<Settings>,
+) {
+    if settings.combat_mode != CombatMode::TurnBased {
+        return;
+    }
+
+    // Everybody waits until the last move has been read and seen.
+    if *turn != CombatTurn::Waiting || !combat_timer.0.finished() || !context.log.is_idle() || !playing.is_done() {
+        return;
+    }
+
+    let (allies, foes) = sides(combatant_query.iter().map(|taker| {
+        (Combatant { entity: taker.entity, stats: *taker.stats }, taker.enemy.is_some())
+    }));
+
+    if order.0.is_empty() {
+        order.0 = speed_order(allies.iter().chain(foes.iter())).into();
+    }
+    let actor = match order.0.pop_front() {
+        Some(actor) => actor,
+        None => return,
+    };
+
+    // Combatants that fell before their turn came up are skipped.
+    let mut taker = match combatant_query.get_mut(actor) {
+        Ok(taker) => taker,
+        Err(_) => return,
+    };
+    if taker.stats.health == 0 {
+        return;
+    }
+    combat_timer.0.reset();
+
+    if !context.start_turn(&mut taker) {
+        taker.effects.on_turn_end();
+        return;
+    }
+
+    match (taker.enemy, taker.ai) {
+        (Some(enemy), Some(ai)) => {
+            context.enemy_act(&mut taker, enemy, ai, &foes, &allies);
+            taker.effects.on_turn_end();
+        }
+        _ => *turn = CombatTurn::Party(taker.entity),
+    }
+}
+
+fn fill_atb_gauges(
+    mut combatant_query: Query<TurnTaker, InFight>,
+    mut context: TurnContext,
+    mut turn: ResMut<CombatTurn>,
+    playing: Playing,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    // Time stands still while there is something to read or watch.
+    if settings.combat_mode != CombatMode::Atb || *turn == CombatTurn::Over || !context.log.is_idle() {
+        return;
+    }
+    if !playing.is_done() {
+        return;
+    }
+
+    let (allies, foes) = sides(combatant_query.iter().map(|taker| {
+        (Combatant { entity: taker.entity, stats: *taker.stats }, taker.enemy.is_some())
+    }));
+
+    for mut taker in combatant_query.iter_mut() {
+        if taker.stats.health == 0 {
+            continue;
+        }
+
+        let speed = taker.stats.speed;
+        if !taker.gauge.fill(speed, time.delta_seconds()) {
+            continue;
+        }
+
+        match (taker.enemy, taker.ai) {
+            (Some(enemy), Some(ai)) => {
+                if context.start_turn(&mut taker) {
+                    context.enemy_act(&mut taker, enemy, ai, &foes, &allies);
+                }
+                taker.effects.on_turn_end();
+                taker.gauge.0 = 0.0;
+            }
+            _ => {
+                // A full gauge stays full while another party member is choosing.
+                if *turn != CombatTurn::Waiting {
+                    continue;
+                }
+
+                if context.start_turn(&mut taker) {
+                    *turn = CombatTurn::Party(taker.entity);
+                } else {
+                    taker.effects.on_turn_end();
+                    taker.gauge.0 = 0.0;
+                }
+            }
+        }
+    }
+}
+
+fn update_timeline(
+    combatant_query: Query<(Entity, &CombatStats, &AtbGauge), InFight>,
+    order: Res<TurnOrder>,
+    turn: Res<CombatTurn>,
+    settings: Res<Settings>,
+    mut timeline: ResMut<Timeline>,
+) {
+    let mut upcoming = Vec::new();
+    if let CombatTurn::Party(member) = *turn {
+        upcoming.push(member);
+    }
+
+    let living: Vec<(Entity, &CombatStats, &AtbGauge)> = combatant_query
+        .iter()
+        .filter(|(entity, stats, _)| stats.health > 0 && !upcoming.contains(entity))
+        .collect();
+
+    match settings.combat_mode {
+        CombatMode::TurnBased => {
+            // The rest of this round, then the next one.
+            upcoming.extend(order.0.iter().filter(|entity| living.iter().any(|(living, _, _)| living == *entity)));
+            let next_round: Vec<Combatant> = living
+                .iter()
+                .map(|(entity, stats, _)| Combatant { entity: *entity, stats: **stats })
+                .collect();
+            upcoming.extend(speed_order(next_round.iter()));
+        }
+        CombatMode::Atb => {
+            let mut waiting: Vec<(Entity, f32)> = living
+                .iter()
+                .map(|(entity, stats, gauge)| (*entity, (1.0 - gauge.0) / std::cmp::max(stats.speed, 1) as f32))
+                .collect();
+            waiting.sort_by(|a, b| a.1.total_cmp(&b.1));
+            upcoming.extend(waiting.into_iter().map(|(entity, _)| entity));
+        }
+    }
+
+    if *turn == CombatTurn::Over {
+        upcoming.clear();
+    }
+    upcoming.truncate(TIMELINELENGTH);
+
+    // Only touch the resource when the order changed, the timeline redraws on change.
+    if timeline.0 != upcoming {
+        timeline.0 = upcoming;
+    }
+}
+
+/// Splits the living combatants into the party and the enemies.
+fn sides(combatants: impl Iterator<Item = (Combatant, bool)>) -> (Vec<Combatant>, Vec<Combatant>) {
+    let (enemies, party): (Vec<_>, Vec<_>) = combatants
+        .filter(|(combatant, _)| combatant.stats.health > 0)
+        .partition(|(_, enemy)| *enemy);
+
+    (
+        party.into_iter().map(|(combatant, _)| combatant).collect(),
+        enemies.into_iter().map(|(combatant, _)| combatant).collect(),
+    )
+}
+
+/// Everyone still standing, fastest first.
+pub fn speed_order<'a>(combatants: impl Iterator<Item = &'a Combatant>) -> Vec<Entity> {
+    let mut living: Vec<&Combatant> = combatants.filter(|combatant| combatant.stats.health > 0).collect();
+    living.sort_by_key(|combatant| std::cmp::Reverse(combatant.stats.speed));
+    living.into_iter().map(|combatant| combatant.entity).collect()
+}
+
+// The rules of a turn, kept apart from the systems so the simulator plays by them too.
+
+/// Lands an event on its target, with the target's defense, affinities and status effects.
+pub fn land_hit(
+    formula: &dyn DamageFormula,
+    rng: &mut StdRng,
+    event: &FightEvent,
+    stats: &mut CombatStats,
+    effects: &mut StatusEffects,
+    affinities: &Affinities,
+) -> HitOutcome {
+    let (roll, effectiveness) = match event.piercing {
+        true => (DamageRoll {
+            damage: event.damage_amount,
+            hit: true,
+            critical: false,
+        }, Effectiveness::Normal),
+        false => resolve_hit(
+            formula,
+            rng,
+            event.damage_amount,
+            effects.modify(stats).defense,
+            affinities,
+            event.damage_type,
+        ),
+    };
+    stats.take_damage(roll.damage);
+
+    let mut outcome = HitOutcome {
+        roll,
+        effectiveness,
+        woke_up: false,
+        status: None,
+    };
+
+    // Piercing events always land, everything else has to hit and get through.
+    if event.piercing || (roll.hit && roll.damage > 0) {
+        if !event.piercing && effects.has(StatusKind::Sleep) {
+            effects.remove(StatusKind::Sleep);
+            outcome.woke_up = true;
+        }
+        if let Some(status) = event.status {
+            effects.apply(status);
+            outcome.status = Some(status);
+        }
+    }
+    outcome
+}
+
+/// Runs the turn start hooks of the active effects. Returns the damage they deal,
+/// which lands before anything else this turn, and whether the combatant may act.
+pub fn begin_turn(entity: Entity, stats: &CombatStats, effects: &mut StatusEffects) -> (Option<FightEvent>, bool) {
+    let outcome = effects.on_turn_start(stats);
+
+    let damage = match outcome.damage {
+        0 => None,
+        damage => Some(FightEvent {
+            target: entity,
+            damage_amount: damage,
+            damage_type: DamageType::Physical,
+            piercing: true,
+            status: None,
+            timed: false,
+        }),
+    };
+    (damage, !outcome.skip_turn)
+}
+
+/// A basic attack, party members and enemies alike.
+pub fn attack_event(target: Entity, attack: isize, damage_type: DamageType, status: Option<StatusKind>) -> FightEvent {
+    FightEvent {
+        target,
+        damage_amount: attack,
+        damage_type,
+        piercing: false,
+        status,
+        timed: true,
+    }
+}
+
+/// The events a skill sends, `target` is the enemy picked for single target skills. Its cost is paid separately.
+pub fn skill_events(skill: &Skill, user: Entity, target: Entity, enemies: &[Entity]) -> Vec<FightEvent> {
+    let targets = match skill.target {
+        SkillTarget::Enemy => vec![target],
+        SkillTarget::AllEnemies => enemies.to_vec(),
+        SkillTarget::Myself => vec![user],
+    };
+
+    targets
+        .into_iter()
+        .map(|target| match skill.effect {
+            SkillEffect::Damage => FightEvent {
+                target,
+                damage_amount: skill.power,
+                damage_type: skill.damage_type,
+                piercing: false,
+                status: None,
+                timed: true,
+            },
+            SkillEffect::Heal => FightEvent {
+                target,
+                damage_amount: -skill.power,
+                damage_type: skill.damage_type,
+                piercing: true,
+                status: None,
+                timed: false,
+            },
+            SkillEffect::Status(status) => FightEvent {
+                target,
+                damage_amount: 0,
+                damage_type: skill.damage_type,
+                piercing: true,
+                status: Some(status),
+                timed: false,
             },
-            ..default()
         })
-        .insert(Enemy)
-        .insert(Name::new(name))
-        .insert(CombatStats {
-            health: hp,
-            max_health: 3,
-            attack: 2,
-            defense: 1,
-        });
-    (name, hp)
+        .collect()
 }
 
-fn spawn_rehu<'a>(
+/// What an enemy decided to do on its turn.
+#[derive(Clone, Copy)]
+pub enum EnemyMove {
+    Attack(Entity), // Walking up to the target first on the battle grid, then `attack_event`.
+    Guard,
+    Heal(FightEvent),
+    Flee(bool), // Whether it got away.
+}
+
+/// Lets the enemy's behavior pick an action. Guarding is applied right away, everything else is up to the caller.
+pub fn enemy_move(
+    ai: &EnemyAi,
+    me: &Combatant,
+    effects: &mut StatusEffects,
+    allies: &[Combatant],
+    foes: &[Combatant],
+    rng: &mut StdRng,
+) -> EnemyMove {
+    match ai.0.choose(me, allies, foes, rng) {
+        EnemyAction::Attack(target) => EnemyMove::Attack(target),
+        EnemyAction::Guard => {
+            effects.apply(StatusKind::Guard);
+            EnemyMove::Guard
+        }
+        EnemyAction::Heal(target, power) => EnemyMove::Heal(FightEvent {
+            target,
+            damage_amount: -power,
+            damage_type: DamageType::Physical,
+            piercing: true,
+            status: None,
+            timed: false,
+        }),
+        EnemyAction::Flee => EnemyMove::Flee(rng.gen_bool(FLEECHANCE)),
+    }
+}
+
+fn combat_camera(mut camera_query: Query<&mut Transform, With<Camera>>) {
+    let mut camera_transform = camera_query.single_mut();
+    camera_transform.translation.x = 0.0;
+    camera_transform.translation.y = 0.0;
+}
+
+fn spawn_enemy(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-) -> (&'a str, isize) {
-    let name = "Rehu";
-    let hp = 5;
+    ascii: Res<AsciiSheet>,
+    mut log: ResMut<CombatLog>,
+    mut rng: ResMut<CombatRng>,
+    pending_boss: Res<PendingBoss>,
+) {
+    // Boss fights bring their own enemy.
+    if pending_boss.0.is_some() {
+        return;
+    }
+
+    // Drawn from the combat rng too, so a seeded fight always meets the same enemy.
+    let enemy_type = EnemyType::ALL[rng.0.gen_range(0..EnemyType::ALL.len())];
+    spawn_combat_enemy(&mut commands, &asset_server, &ascii, enemy_type, Vec3::new(0.0, 0.2, 40.0));
+
+    log.push(format!("A wild {} appears! It has {} hp.", enemy_type.name(), enemy_type.stats().health));
+}
+
+/// Spawns an enemy with its health bar into the current fight.
+pub fn spawn_combat_enemy(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    ascii: &AsciiSheet,
+    enemy_type: EnemyType,
+    translation: Vec3,
+) -> Entity {
+    let stats = enemy_type.stats();
+    let scale = enemy_type.scale();
 
-    commands.spawn_bundle(SpriteBundle {
-            texture: asset_server.load("rehu.png"),
+    let enemy = commands.spawn_bundle(SpriteBundle {
+            texture: asset_server.load(enemy_type.texture()),
             transform: Transform {
-                translation: Vec3::new(0.0, 0.2, 0.0),
-                scale: Vec3::new(0.001, 0.001, 1.0),
+                translation,
+                scale: Vec3::new(scale, scale, 1.0),
                 ..default()
             },
             ..default()
         })
-        .insert(Enemy)
-        .insert(Name::new(name))
-        .insert(CombatStats {
-            health: hp,
-            max_health: 3,
-            attack: 2,
-            defense: 1,
-        });
-    (name, hp)
-}
+        .insert(Enemy {
+            enemy_type,
+            attack_type: enemy_type.attack_type(),
+        })
+        .insert(Name::new(enemy_type.name()))
+        .insert(stats)
+        .insert(StatusEffects::default())
+        .insert(enemy_type.affinities())
+        .insert(AtbGauge::default())
+        .insert(enemy_type.ai())
+        .id();
+
+    spawn_health_bar(
+        commands,
+        ascii,
+        enemy,
+        &stats,
+        Vec3::new(translation.x, translation.y + ENEMYBAROFFSET, 50.0),
+    );
 
+    enemy
+}
 
 /*
 fn spawn_enemy(mut commands: Commands, ascii:Res<AsciiSheet>) {
@@ -206,7 +1274,13 @@ fn spawn_enemy(mut commands: Commands, ascii:Res<AsciiSheet>) {
 }
 */
 
-fn despawn_enemies(mut commands: Commands, enemy_query: Query<Entity, With<Enemy>>) {
+/// The enemies and the health bars floating above them.
+type EnemyEntities = Or<(With<Enemy>, With<HealthBar>)>;
+
+fn despawn_enemies(
+    mut commands: Commands,
+    enemy_query: Query<Entity, EnemyEntities>,
+) {
     for entity in enemy_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, math::const_vec3};
+
+use crate::{
+    GameState, TILESIZE,
+    actions::{Action, Actions},
+    ascii::{AsciiSheet, spawn_ascii_text},
+};
+
+const LOGPOSITION: Vec3 = const_vec3!([-1.7, 0.85, 850.0]);
+const LOGWIDTH: usize = 14; // Characters per line.
+const LOGLINES: usize = 4;
+const TYPESPEED: f32 = 0.03; // Seconds per character.
+
+/// The messages shown in the log panel during combat.
+#[derive(Default)]
+pub struct CombatLog {
+    lines: Vec<String>,
+    pending: VecDeque<String>,
+    current: Option<String>,
+    typed: usize, // Characters, not bytes, names from the data files may hold accents.
+}
+
+/// Paces the typing. Kept out of `CombatLog`, so the log only counts as changed when its text does.
+struct TypeTimer(Timer);
+
+impl Default for TypeTimer {
+    fn default() -> Self {
+        TypeTimer(Timer::from_seconds(TYPESPEED, true))
+    }
+}
+
+impl CombatLog {
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.pending.push_back(message.into());
+    }
+
+    /// Whether every message has been typed out and read.
+    pub fn is_idle(&self) -> bool {
+        self.current.is_none() && self.pending.is_empty()
+    }
+
+    fn is_typing(&self) -> bool {
+        match &self.current {
+            Some(message) => self.typed < message.chars().count(),
+            None => false,
+        }
+    }
+
+    fn next_message(&mut self) {
+        if let Some(message) = self.current.take() {
+            self.lines.push(message);
+            if self.lines.len() > LOGLINES {
+                self.lines.remove(0);
+            }
+        }
+        self.current = self.pending.pop_front();
+        self.typed = 0;
+    }
+
+    /// The wrapped lines that fit into the panel, the one being typed at the bottom.
+    fn visible_lines(&self) -> Vec<String> {
+        let mut wrapped: Vec<String> = self.lines.iter().flat_map(|line| wrap(line)).collect();
+        if let Some(message) = &self.current {
+            wrapped.extend(wrap(&message.chars().take(self.typed).collect::<String>()));
+        }
+
+        let skip = wrapped.len().saturating_sub(LOGLINES);
+        wrapped.into_iter().skip(skip).collect()
+    }
+}
+
+#[derive(Component)]
+struct LogPanel;
+
+#[derive(Component)]
+struct LogLine;
+
+pub struct CombatLogPlugin;
+
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLog>()
+            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(spawn_log_panel))
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(type_log.label("combat_log"))
+                .with_system(draw_log.after("combat_log"))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_log_panel));
+    }
+}
+
+fn spawn_log_panel(mut commands: Commands, ascii: Res<AsciiSheet>) {
+    let width = LOGWIDTH as f32 * TILESIZE;
+    let height = LOGLINES as f32 * TILESIZE;
+
+    let mut sprite = TextureAtlasSprite::new(0);
+    sprite.color = Color::rgba(0.05, 0.05, 0.1, 0.8);
+    sprite.custom_size = Some(Vec2::new(width + TILESIZE, height + TILESIZE));
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite,
+            texture_atlas: ascii.0.clone(),
+            transform: Transform {
+                translation: LOGPOSITION
+                    + Vec3::new((width - TILESIZE) / 2.0, -(height - TILESIZE) / 2.0, -1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("CombatLog"))
+        .insert(LogPanel);
+}
+
+fn type_log(
+    mut log: ResMut<CombatLog>,
+    mut actions: ResMut<Actions>,
+    mut timer: Local<TypeTimer>,
+    time: Res<Time>,
+) {
+    // Confirm is eaten here while there is something to read, so it does not also attack.
+    if actions.just_pressed(Action::Confirm) && !log.is_idle() {
+        actions.clear_just_pressed(Action::Confirm);
+        if log.is_typing() {
+            log.typed = log.current.as_ref().map_or(0, |message| message.chars().count());
+        } else {
+            log.next_message();
+        }
+        return;
+    }
+
+    if log.current.is_none() && !log.pending.is_empty() {
+        log.next_message();
+    }
+
+    if log.is_typing() {
+        if timer.0.tick(time.delta()).just_finished() {
+            log.typed += 1;
+        }
+    } else if log.current.is_some() && log.pending.is_empty() {
+        // Nothing left to wait for, the message simply stays on screen.
+        log.next_message();
+    }
+}
+
+fn draw_log(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    log: Res<CombatLog>,
+    line_query: Query<Entity, With<LogLine>>,
+) {
+    if !log.is_changed() {
+        return;
+    }
+
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+
+    for (i, text) in log.visible_lines().iter().enumerate() {
+        let line = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            text,
+            Color::rgb(0.9, 0.9, 0.9),
+            LOGPOSITION - Vec3::new(0.0, i as f32 * TILESIZE, 0.0),
+        );
+        commands.entity(line).insert(LogLine);
+    }
+}
+
+type LogEntities = Or<(With<LogPanel>, With<LogLine>)>;
+
+fn despawn_log_panel(
+    mut commands: Commands,
+    mut log: ResMut<CombatLog>,
+    panel_query: Query<Entity, LogEntities>,
+) {
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *log = CombatLog::default();
+}
+
+/// Breaks a message into lines of at most `LOGWIDTH` characters, at spaces where possible.
+fn wrap(message: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in message.split(' ') {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > LOGWIDTH {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+
+        while line.chars().count() > LOGWIDTH {
+            let rest: String = line.chars().skip(LOGWIDTH).collect();
+            line = line.chars().take(LOGWIDTH).collect();
+            lines.push(std::mem::replace(&mut line, rest));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
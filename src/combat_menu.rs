@@ -0,0 +1,190 @@
+use bevy::{prelude::*, ecs::system::SystemParam, math::const_vec3};
+
+use crate::{
+    GameState, TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_text},
+    combat::{CombatStats, CombatTurn},
+    grid::GridTurn,
+    actions::{Action, Actions},
+    skills::{KnownSkills, SkillBook},
+};
+
+const MENUPOSITION: Vec3 = const_vec3!([0.1, -0.3, 800.0]);
+const MENULINES: usize = 5; // Longer skill lists scroll.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CombatAction {
+    Attack,
+    Skill(usize), // Index into the `SkillBook`.
+    Wait, // Ends the turn without acting, e.g. when nobody is in reach on the battle grid.
+}
+
+/// Which entry of the acting party member's action menu is highlighted.
+pub struct CombatMenu {
+    pub selected: usize,
+}
+
+#[derive(Component)]
+struct MenuLine;
+
+pub struct CombatMenuPlugin;
+
+impl Plugin for CombatMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CombatMenu { selected: 0 })
+            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(reset_menu))
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(menu_navigation.after("grid_movement"))
+                .with_system(draw_menu.after(menu_navigation))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_menu));
+    }
+}
+
+/// Everything the player can choose from, in menu order.
+pub fn combat_actions(known: &KnownSkills) -> Vec<CombatAction> {
+    let mut actions = vec![CombatAction::Attack];
+    actions.extend(known.0.iter().map(|&index| CombatAction::Skill(index)));
+    actions.push(CombatAction::Wait);
+    actions
+}
+
+impl CombatMenu {
+    pub fn action(&self, known: &KnownSkills) -> CombatAction {
+        let actions = combat_actions(known);
+        actions[self.selected.min(actions.len() - 1)]
+    }
+}
+
+fn reset_menu(mut menu: ResMut<CombatMenu>) {
+    menu.selected = 0;
+}
+
+fn menu_navigation(
+    actions: Res<Actions>,
+    member_query: Query<&KnownSkills>,
+    turn: Res<CombatTurn>,
+    mut grid_turn: ResMut<GridTurn>,
+    mut menu: ResMut<CombatMenu>,
+) {
+    let known = match *turn {
+        CombatTurn::Party(member) => match member_query.get(member) {
+            Ok(known) => known,
+            Err(_) => return,
+        },
+        _ => return,
+    };
+
+    // Every member's menu opens on its first entry.
+    if turn.is_changed() {
+        menu.selected = 0;
+    }
+
+    // The keys walk on the battle grid first.
+    if grid_turn.moving {
+        return;
+    }
+
+    // Cancel steps back out of the menu to the walk on the battle grid, or to the first entry.
+    if actions.just_pressed(Action::Cancel) && !grid_turn.resume() {
+        menu.selected = 0;
+    }
+
+    let entries = combat_actions(known).len();
+
+    if actions.just_pressed(Action::MoveUp) {
+        menu.selected = (menu.selected + entries - 1) % entries;
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        menu.selected = (menu.selected + 1) % entries;
+    }
+}
+
+/// The menu shows the member's mana and skills, so it redraws when they change.
+type MemberChanged = Or<(Changed<CombatStats>, Changed<KnownSkills>)>;
+
+/// Whose menu is open and what is highlighted in it.
+#[derive(SystemParam)]
+struct MenuState<'w, 's> {
+    menu: Res<'w, CombatMenu>,
+    turn: Res<'w, CombatTurn>,
+    grid_turn: Res<'w, GridTurn>,
+    changed_query: Query<'w, 's, (), MemberChanged>,
+}
+
+impl MenuState<'_, '_> {
+    fn is_changed(&self) -> bool {
+        self.menu.is_changed() || self.turn.is_changed() || self.grid_turn.is_changed() || !self.changed_query.is_empty()
+    }
+}
+
+fn draw_menu(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    book: Res<SkillBook>,
+    member_query: Query<(&Name, &CombatStats, &KnownSkills)>,
+    line_query: Query<Entity, With<MenuLine>>,
+    state: MenuState,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let MenuState { menu, turn, grid_turn, .. } = state;
+
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+    if grid_turn.moving {
+        return;
+    }
+
+    // The menu only opens when a party member may act.
+    let (name, stats, known) = match *turn {
+        CombatTurn::Party(member) => match member_query.get(member) {
+            Ok(member) => member,
+            Err(_) => return,
+        },
+        _ => return,
+    };
+
+    let title = spawn_ascii_text(&mut commands, &ascii, name.as_str(), Color::rgb(0.4, 0.6, 0.95), MENUPOSITION);
+    commands.entity(title).insert(MenuLine);
+
+    let actions = combat_actions(known);
+    let first = menu.selected.saturating_sub(MENULINES - 1).min(actions.len().saturating_sub(MENULINES));
+
+    for (row, (i, action)) in actions.iter().enumerate().skip(first).take(MENULINES).enumerate() {
+        let (text, affordable) = match action {
+            CombatAction::Attack => ("Attack".to_string(), true),
+            CombatAction::Wait => ("Wait".to_string(), true),
+            CombatAction::Skill(index) => {
+                let skill = &book.0[*index];
+                (format!("{} {}MP", skill.name, skill.cost), skill.cost <= stats.mana)
+            }
+        };
+
+        let selected = i == menu.selected;
+        let color = match (selected, affordable) {
+            (true, true) => Color::rgb(0.95, 0.8, 0.1),
+            (true, false) => Color::rgb(0.6, 0.5, 0.1),
+            (false, true) => Color::rgb(0.9, 0.9, 0.9),
+            (false, false) => Color::rgb(0.4, 0.4, 0.4),
+        };
+        let cursor = if selected { '>' } else { ' ' };
+
+        let line = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            &format!("{}{}", cursor, text),
+            color,
+            MENUPOSITION - Vec3::new(0.0, (row + 1) as f32 * TILESIZE * 1.2, 0.0),
+        );
+        commands.entity(line).insert(MenuLine);
+    }
+}
+
+fn despawn_menu(mut commands: Commands, line_query: Query<Entity, With<MenuLine>>) {
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    actions::{Action, Actions},
+    ascii::AsciiSheet,
+    element::{Affinities, DamageType, Effectiveness},
+    popup::spawn_banner,
+    settings::Settings,
+};
+
+/// The outcome of one attack, before the target's affinities are applied.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DamageRoll {
+    pub damage: isize,
+    pub hit: bool,
+    pub critical: bool,
+}
+
+impl DamageRoll {
+    pub fn miss() -> Self {
+        DamageRoll {
+            damage: 0,
+            hit: false,
+            critical: false,
+        }
+    }
+}
+
+/// Turns an attack value and a defense value into damage.
+pub trait DamageFormula: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn resolve(&self, attack: isize, defense: isize, rng: &mut StdRng) -> DamageRoll;
+}
+
+/// Attack minus defense with hit rolls, critical hits and some variance.
+pub struct StandardFormula {
+    pub min_damage: isize,
+    pub hit_chance: f64,
+    pub crit_chance: f64,
+    pub crit_multiplier: f32,
+    pub variance: f32, // Damage is scaled by a random factor in 1 +- variance.
+}
+
+impl Default for StandardFormula {
+    fn default() -> Self {
+        StandardFormula {
+            min_damage: 1,
+            hit_chance: 0.9,
+            crit_chance: 0.1,
+            crit_multiplier: 1.5,
+            variance: 0.2,
+        }
+    }
+}
+
+impl DamageFormula for StandardFormula {
+    fn name(&self) -> &'static str {
+        "standard"
+    }
+
+    fn resolve(&self, attack: isize, defense: isize, rng: &mut StdRng) -> DamageRoll {
+        if !rng.gen_bool(self.hit_chance) {
+            return DamageRoll::miss();
+        }
+
+        let critical = rng.gen_bool(self.crit_chance);
+        let mut damage = (attack - defense) as f32 * rng.gen_range(1.0 - self.variance..=1.0 + self.variance);
+        if critical {
+            damage *= self.crit_multiplier;
+        }
+
+        DamageRoll {
+            damage: std::cmp::max(damage.round() as isize, self.min_damage),
+            hit: true,
+            critical,
+        }
+    }
+}
+
+/// Plain attack minus defense, never missing and never below the floor.
+pub struct ClassicFormula {
+    pub min_damage: isize,
+}
+
+impl DamageFormula for ClassicFormula {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+
+    fn resolve(&self, attack: isize, defense: isize, _rng: &mut StdRng) -> DamageRoll {
+        DamageRoll {
+            damage: std::cmp::max(attack - defense, self.min_damage),
+            hit: true,
+            critical: false,
+        }
+    }
+}
+
+/// Rolls an attack with `formula` and applies the target's affinities to the result.
+pub fn resolve_hit(
+    formula: &dyn DamageFormula,
+    rng: &mut StdRng,
+    attack: isize,
+    defense: isize,
+    affinities: &Affinities,
+    damage_type: DamageType,
+) -> (DamageRoll, Effectiveness) {
+    let mut roll = formula.resolve(attack, defense, rng);
+    if !roll.hit {
+        return (roll, Effectiveness::Normal);
+    }
+
+    let effectiveness = affinities.effectiveness(damage_type);
+    if effectiveness != Effectiveness::Normal {
+        roll.damage = (roll.damage as f32 * affinities.multiplier(damage_type)).round() as isize;
+    }
+    (roll, effectiveness)
+}
+
+/// The formula combat currently uses, swap it to try a different balance.
+pub struct DamageModel(pub Box<dyn DamageFormula>);
+
+/// All randomness in combat goes through here, so fights can be replayed from a seed.
+pub struct CombatRng(pub StdRng);
+
+impl CombatRng {
+    pub fn from_seed(seed: u64) -> Self {
+        CombatRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DamageModel(Box::new(StandardFormula::default())))
+            .insert_resource(CombatRng(StdRng::from_entropy()))
+            .add_startup_system(seed_combat_rng);
+
+        if cfg!(debug_assertions) {
+            app.add_system(switch_formula);
+        }
+    }
+}
+
+fn seed_combat_rng(settings: Res<Settings>, mut rng: ResMut<CombatRng>) {
+    if let Some(seed) = settings.combat_seed {
+        *rng = CombatRng::from_seed(seed);
+    }
+}
+
+// Lets designers flip between the formulas while playing.
+fn switch_formula(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    ascii: Res<AsciiSheet>,
+    camera_query: Query<Entity, With<Camera>>,
+    mut model: ResMut<DamageModel>,
+) {
+    if actions.just_pressed(Action::SwitchFormula) {
+        model.0 = match model.0.name() {
+            "standard" => Box::new(ClassicFormula { min_damage: 1 }),
+            _ => Box::new(StandardFormula::default()),
+        };
+        spawn_banner(
+            &mut commands,
+            &ascii,
+            camera_query.single(),
+            &format!("Using the {} damage formula.", model.0.name()),
+            Color::rgb(0.9, 0.9, 0.9),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: u64 = 200;
+
+    /// A standard formula that always hits and never rolls a critical hit or any variance.
+    fn steady() -> StandardFormula {
+        StandardFormula {
+            hit_chance: 1.0,
+            crit_chance: 0.0,
+            variance: 0.0,
+            ..StandardFormula::default()
+        }
+    }
+
+    #[test]
+    fn defense_above_attack_deals_the_floor() {
+        let formula = StandardFormula {
+            hit_chance: 1.0,
+            ..StandardFormula::default()
+        };
+        for seed in 0..SEEDS {
+            let roll = formula.resolve(1, 5, &mut StdRng::seed_from_u64(seed));
+            assert!(roll.hit);
+            assert_eq!(roll.damage, formula.min_damage);
+        }
+
+        let classic = ClassicFormula { min_damage: 1 };
+        assert_eq!(classic.resolve(1, 5, &mut StdRng::seed_from_u64(0)).damage, 1);
+    }
+
+    #[test]
+    fn damage_never_drops_below_the_floor() {
+        let formula = StandardFormula::default();
+        for seed in 0..SEEDS {
+            let roll = formula.resolve(3, 2, &mut StdRng::seed_from_u64(seed));
+            if roll.hit {
+                assert!(roll.damage >= formula.min_damage);
+            }
+        }
+    }
+
+    #[test]
+    fn misses_deal_nothing() {
+        let formula = StandardFormula {
+            hit_chance: 0.0,
+            ..StandardFormula::default()
+        };
+        let weak = Affinities(vec![(DamageType::Fire, 2.0)]);
+        for seed in 0..SEEDS {
+            let (roll, effectiveness) = resolve_hit(&formula, &mut StdRng::seed_from_u64(seed), 10, 0, &weak, DamageType::Fire);
+            assert_eq!(roll, DamageRoll::miss());
+            assert_eq!(effectiveness, Effectiveness::Normal);
+        }
+    }
+
+    #[test]
+    fn critical_hits_multiply_damage() {
+        let formula = StandardFormula {
+            crit_chance: 1.0,
+            ..steady()
+        };
+        let roll = formula.resolve(5, 1, &mut StdRng::seed_from_u64(0));
+        assert!(roll.critical);
+        assert_eq!(roll.damage, 6);
+    }
+
+    #[test]
+    fn affinities_scale_a_hit() {
+        let formula = steady();
+        let affinities = Affinities(vec![(DamageType::Fire, 2.0), (DamageType::Earth, 0.0)]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let (roll, effectiveness) = resolve_hit(&formula, &mut rng, 4, 1, &affinities, DamageType::Fire);
+        assert_eq!((roll.damage, effectiveness), (6, Effectiveness::Weak));
+
+        // Immunity goes below the floor, the floor only keeps defense from cancelling a hit.
+        let (roll, effectiveness) = resolve_hit(&formula, &mut rng, 1, 5, &affinities, DamageType::Earth);
+        assert_eq!((roll.damage, effectiveness), (0, Effectiveness::Immune));
+
+        let (roll, effectiveness) = resolve_hit(&formula, &mut rng, 4, 1, &affinities, DamageType::Water);
+        assert_eq!((roll.damage, effectiveness), (3, Effectiveness::Normal));
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::{WorldInspectorPlugin, RegisterInspectable};
 
-use crate::player::Player;
+use crate::{player::Player, experience::Experience};
 
 pub struct DebugPlugin;
 
@@ -9,7 +9,8 @@ impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         if cfg!(debug_assertions) {
             app.add_plugin(WorldInspectorPlugin::new())
-                .register_inspectable::<Player>();
+                .register_inspectable::<Player>()
+                .register_inspectable::<Experience>();
         }
     }
 }
\ No newline at end of file
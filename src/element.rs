@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Water,
+    Earth,
+    Air,
+}
+
+impl DamageType {
+    pub fn parse(name: &str) -> Option<DamageType> {
+        match name {
+            "physical" => Some(DamageType::Physical),
+            "fire" => Some(DamageType::Fire),
+            "water" => Some(DamageType::Water),
+            "earth" => Some(DamageType::Earth),
+            "air" => Some(DamageType::Air),
+            _ => None,
+        }
+    }
+}
+
+/// How well a combatant takes a hit of some damage type.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Effectiveness {
+    Normal,
+    Weak,
+    Resistant,
+    Immune,
+}
+
+impl Effectiveness {
+    /// The line shown when a hit is anything but normal.
+    pub fn message(&self) -> Option<&'static str> {
+        match self {
+            Effectiveness::Normal => None,
+            Effectiveness::Weak => Some("It's super effective!"),
+            Effectiveness::Resistant => Some("It's not very effective..."),
+            Effectiveness::Immune => Some("It has no effect!"),
+        }
+    }
+
+    /// The short form that pops up over the target.
+    pub fn popup(&self) -> Option<&'static str> {
+        match self {
+            Effectiveness::Normal => None,
+            Effectiveness::Weak => Some("WEAK"),
+            Effectiveness::Resistant => Some("RESIST"),
+            Effectiveness::Immune => Some("IMMUNE"),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Effectiveness::Weak => Color::rgb(0.95, 0.5, 0.1),
+            Effectiveness::Resistant | Effectiveness::Immune => Color::rgb(0.5, 0.5, 0.6),
+            Effectiveness::Normal => Color::rgb(0.9, 0.9, 0.9),
+        }
+    }
+}
+
+/// Damage multipliers per type, anything not listed takes normal damage.
+#[derive(Component, Clone, Default)]
+pub struct Affinities(pub Vec<(DamageType, f32)>);
+
+impl Affinities {
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        self.0
+            .iter()
+            .find(|(affinity, _)| *affinity == damage_type)
+            .map_or(1.0, |(_, multiplier)| *multiplier)
+    }
+
+    pub fn effectiveness(&self, damage_type: DamageType) -> Effectiveness {
+        let multiplier = self.multiplier(damage_type);
+        if multiplier == 0.0 {
+            Effectiveness::Immune
+        } else if multiplier > 1.0 {
+            Effectiveness::Weak
+        } else if multiplier < 1.0 {
+            Effectiveness::Resistant
+        } else {
+            Effectiveness::Normal
+        }
+    }
+}
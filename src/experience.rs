@@ -0,0 +1,172 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+use crate::{
+    GameState,
+    ascii::AsciiSheet,
+    combat::CombatStats,
+    combat_log::CombatLog,
+    player::Player,
+    popup::spawn_banner,
+};
+
+/// Sent when the player earns experience, e.g. by defeating an enemy.
+pub struct ExperienceEvent(pub usize);
+
+#[derive(Component, Inspectable)]
+pub struct Experience {
+    pub level: usize,
+    pub points: usize,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience { level: 1, points: 0 }
+    }
+}
+
+/// How much experience each level costs and what it gives, loaded from `assets/levels.txt`.
+pub struct LevelCurve {
+    pub base: f32,
+    pub growth: f32,
+    pub health_per_level: isize,
+    pub attack_per_level: isize,
+    pub defense_per_level: isize,
+    pub mana_per_level: isize,
+    pub speed_per_level: isize,
+}
+
+impl Default for LevelCurve {
+    fn default() -> Self {
+        LevelCurve {
+            base: 5.0,
+            growth: 1.5,
+            health_per_level: 3,
+            attack_per_level: 1,
+            defense_per_level: 1,
+            mana_per_level: 2,
+            speed_per_level: 1,
+        }
+    }
+}
+
+impl LevelCurve {
+    /// The experience needed to get from `level` to the next one.
+    pub fn points_to_next(&self, level: usize) -> usize {
+        // A level for free would level up forever.
+        ((self.base * self.growth.powi(level as i32 - 1)).round() as usize).max(1)
+    }
+
+    pub fn level_up(&self, stats: &mut CombatStats) {
+        stats.max_health += self.health_per_level;
+        stats.heal(self.health_per_level);
+        stats.attack += self.attack_per_level;
+        stats.defense += self.defense_per_level;
+        stats.max_mana += self.mana_per_level;
+        stats.mana += self.mana_per_level;
+        stats.speed += self.speed_per_level;
+    }
+}
+
+/// Levels reached during combat, announced once the player is back in the overworld.
+struct PendingLevelUps(Vec<usize>);
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_level_curve)
+            .insert_resource(PendingLevelUps(Vec::new()))
+            .add_event::<ExperienceEvent>()
+            .add_system(gain_experience)
+            .add_system_set(SystemSet::on_enter(GameState::Overworld).with_system(announce_level_ups));
+    }
+}
+
+fn load_level_curve(mut commands: Commands) {
+    commands.insert_resource(read_level_curve());
+}
+
+/// Reads `assets/levels.txt`, values left out keep their defaults. The simulator levels up the same way.
+pub fn read_level_curve() -> LevelCurve {
+    let mut curve = LevelCurve::default();
+
+    let file = match File::open("assets/levels.txt") {
+        Ok(file) => file,
+        Err(_) => return curve,
+    };
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                println!("Invalid level curve line: {}", line);
+                continue;
+            }
+        };
+
+        let parsed = match key {
+            "base" => value.parse().map(|base| curve.base = base).is_ok(),
+            "growth" => value.parse().map(|growth| curve.growth = growth).is_ok(),
+            "health" => value.parse().map(|health| curve.health_per_level = health).is_ok(),
+            "attack" => value.parse().map(|attack| curve.attack_per_level = attack).is_ok(),
+            "defense" => value.parse().map(|defense| curve.defense_per_level = defense).is_ok(),
+            "mana" => value.parse().map(|mana| curve.mana_per_level = mana).is_ok(),
+            "speed" => value.parse().map(|speed| curve.speed_per_level = speed).is_ok(),
+            _ => {
+                println!("Unknown level curve value: {}", key);
+                continue;
+            }
+        };
+        if !parsed {
+            println!("Invalid {} in the level curve: {}", key, value);
+        }
+    }
+
+    curve
+}
+
+fn gain_experience(
+    mut experience_event: EventReader<ExperienceEvent>,
+    mut player_query: Query<(&mut Experience, &mut CombatStats), With<Player>>,
+    curve: Res<LevelCurve>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut log: ResMut<CombatLog>,
+) {
+    for event in experience_event.iter() {
+        let (mut experience, mut stats) = player_query.single_mut();
+
+        experience.points += event.0;
+        log.push(format!("Gained {} experience.", event.0));
+
+        while experience.points >= curve.points_to_next(experience.level) {
+            experience.points -= curve.points_to_next(experience.level);
+            experience.level += 1;
+            curve.level_up(&mut stats);
+            pending.0.push(experience.level);
+            log.push(format!("Reached level {}!", experience.level));
+        }
+    }
+}
+
+fn announce_level_ups(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    camera_query: Query<Entity, With<Camera>>,
+    mut pending: ResMut<PendingLevelUps>,
+) {
+    // Several levels from one fight share a banner.
+    let text = match pending.0.as_slice() {
+        [] => return,
+        [level] => format!("LEVEL UP! LV {}", level),
+        [.., level] => format!("{} LEVEL UPS! LV {}", pending.0.len(), level),
+    };
+    spawn_banner(&mut commands, &ascii, camera_query.single(), &text, Color::rgb(0.95, 0.8, 0.1));
+    pending.0.clear();
+}
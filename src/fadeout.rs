@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ecs::system::SystemParam};
 
 use crate::{GameState, ascii::AsciiSheet};
 
@@ -46,6 +46,28 @@ fn fadeout(
     }
 }
 
+/// Fades the screen over to another state, unless a fade is already running.
+#[derive(SystemParam)]
+pub struct Fader<'w, 's> {
+    fade_query: Query<'w, 's, (), With<ScreenFade>>,
+    ascii: Res<'w, AsciiSheet>,
+    timer: ResMut<'w, FadeoutTimer>,
+}
+
+impl Fader<'_, '_> {
+    pub fn is_fading(&self) -> bool {
+        !self.fade_query.is_empty()
+    }
+
+    pub fn fade_to(&mut self, commands: &mut Commands, next_state: GameState) {
+        // A second fade would queue a second state change on top of the first.
+        if self.is_fading() {
+            return;
+        }
+        create_fadeout(commands, next_state, &self.ascii, &mut self.timer);
+    }
+}
+
 pub fn create_fadeout<'a>(
     commands: &mut Commands,
     next_state: GameState,
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, TILESIZE,
+    actions::{Action, Actions},
+    boss::DefeatedBosses,
+    ascii::{AsciiSheet, spawn_ascii_text},
+    combat::CombatStats,
+    experience::Experience,
+    fadeout::Fader,
+    inventory::Inventory,
+    kolleg::{Kolleg, spawn_kolleg},
+    player::{Player, PLAYERSTART, starting_stats},
+    save::read_save,
+};
+
+#[derive(Component)]
+struct GameOverScreen;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(spawn_game_over_screen))
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(game_over_input))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(despawn_game_over_screen));
+    }
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    actions: Res<Actions>,
+    camera_query: Query<&Transform, With<Camera>>,
+) {
+    let center = camera_query.single().translation;
+
+    // Without a save, loading starts over as well.
+    let load = match read_save() {
+        Some(_) => "LOAD LAST SAVE",
+        None => "NO SAVE, RESTART",
+    };
+    let lines = [
+        ("GAME OVER".to_string(), Color::rgb(0.9, 0.2, 0.2), 0.3),
+        (format!("{} - {}", actions.key_name(Action::Confirm), load), Color::rgb(0.9, 0.9, 0.9), 0.0),
+        (format!("{} - RESTART", actions.key_name(Action::Restart)), Color::rgb(0.9, 0.9, 0.9), -TILESIZE * 1.5),
+    ];
+
+    for (text, color, height) in lines {
+        let left_center = Vec3::new(
+            center.x - (text.len() as f32 - 1.0) * TILESIZE / 2.0,
+            center.y + height,
+            800.0,
+        );
+        let line = spawn_ascii_text(&mut commands, &ascii, &text, color, left_center);
+        commands.entity(line).insert(GameOverScreen);
+    }
+}
+
+fn game_over_input(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_query: Query<(&mut CombatStats, &mut Experience, &mut Transform, &mut Inventory), With<Player>>,
+    kolleg_query: Query<Entity, With<Kolleg>>,
+    mut defeated: ResMut<DefeatedBosses>,
+    mut fader: Fader,
+) {
+    // Already on the way out.
+    if fader.is_fading() {
+        return;
+    }
+
+    let (mut stats, mut experience, mut transform, mut inventory) = player_query.single_mut();
+
+    let load = actions.just_pressed(Action::Confirm);
+    let restart = actions.just_pressed(Action::Restart);
+    if !load && !restart {
+        return;
+    }
+
+    // The roster is rebuilt from the save, or starts out empty.
+    for kolleg in kolleg_query.iter() {
+        commands.entity(kolleg).despawn_recursive();
+    }
+
+    match read_save().filter(|_| load) {
+        Some(save) => {
+            *stats = save.stats;
+            experience.level = save.level;
+            experience.points = save.experience;
+            transform.translation = save.translation;
+            *inventory = save.inventory;
+            defeated.0 = save.defeated_bosses;
+            for kolleg in save.kollegs {
+                spawn_kolleg(&mut commands, kolleg.enemy_type, kolleg.stats, kolleg.active);
+            }
+        }
+        None => {
+            *stats = starting_stats();
+            *experience = Experience::default();
+            transform.translation = PLAYERSTART;
+            *inventory = Inventory::default();
+            defeated.0.clear();
+        }
+    }
+
+    fader.fade_to(&mut commands, GameState::Overworld);
+}
+
+fn despawn_game_over_screen(mut commands: Commands, screen_query: Query<Entity, With<GameOverScreen>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{prelude::*, math::{const_ivec2, const_vec3}};
+
+use crate::{
+    GameState, TILESIZE,
+    actions::{Action, Actions},
+    ascii::{AsciiSheet, spawn_ascii_sprite, spawn_ascii_text},
+    combat::{CombatStats, CombatTurn, Enemy, PartyMember},
+    combat_log::CombatLog,
+    player::Player,
+    settings::Settings,
+    tilemap::{Map, TileCollider},
+};
+
+const GRIDRADIUS: i32 = 3; // Tiles around the player, so the grid is 7 by 7.
+const GRIDCENTER: Vec3 = const_vec3!([0.2, 0.32, 300.0]);
+const GRIDSCALE: f32 = 1.2;
+const MOVERANGE: usize = 3; // Tiles a combatant may walk per turn.
+const ENEMYDISTANCE: usize = 4; // How far from the player enemies show up, if the grid has room.
+
+const DIRECTIONS: [IVec2; 4] = [const_ivec2!([1, 0]), const_ivec2!([0, 1]), const_ivec2!([-1, 0]), const_ivec2!([0, -1])];
+
+/// Where a combatant stands on the battle grid, rows count downwards like in the map file.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridPosition(pub IVec2);
+
+impl GridPosition {
+    /// Whether `other` stands on a neighbouring cell, diagonals don't count.
+    pub fn is_adjacent(&self, other: &GridPosition) -> bool {
+        let distance = (self.0 - other.0).abs();
+        distance.x + distance.y == 1
+    }
+}
+
+/// The cells of a grid fight, copied from the overworld around the player.
+pub struct BattleGrid {
+    size: i32,
+    tiles: Vec<(usize, bool)>, // Character and whether it is a `TileCollider`, row by row.
+}
+
+impl BattleGrid {
+    fn is_free(&self, cell: IVec2) -> bool {
+        if cell.x < 0 || cell.y < 0 || cell.x >= self.size || cell.y >= self.size {
+            return false;
+        }
+        !self.tiles[(cell.y * self.size + cell.x) as usize].1
+    }
+
+    /// Walking distances from `start` around walls and `occupied` cells, with the cell each step came from.
+    fn paths(&self, start: IVec2, occupied: &[IVec2]) -> HashMap<IVec2, (usize, IVec2)> {
+        let mut paths = HashMap::new();
+        let mut queue = VecDeque::new();
+        paths.insert(start, (0, start));
+        queue.push_back(start);
+
+        while let Some(cell) = queue.pop_front() {
+            let distance = paths[&cell].0;
+            for direction in DIRECTIONS {
+                let next = cell + direction;
+                if !self.is_free(next) || occupied.contains(&next) || paths.contains_key(&next) {
+                    continue;
+                }
+                paths.insert(next, (distance + 1, cell));
+                queue.push_back(next);
+            }
+        }
+        paths
+    }
+
+    /// Walks `mover` up to its move range towards `target`, returns whether it ends up next to it.
+    pub fn approach(&self, positions: &mut Query<&mut GridPosition>, mover: Entity, target: Entity) -> bool {
+        let (start, goal) = match (positions.get(mover), positions.get(target)) {
+            (Ok(start), Ok(goal)) => (*start, *goal),
+            // Somebody off the grid can always be reached.
+            _ => return true,
+        };
+        if start.is_adjacent(&goal) {
+            return true;
+        }
+
+        let occupied: Vec<IVec2> = positions.iter().map(|position| position.0).filter(|cell| *cell != start.0).collect();
+        let paths = self.paths(start.0, &occupied);
+
+        // The closest free cell next to the target, then back along the path until it is in range.
+        let mut cell = match DIRECTIONS
+            .iter()
+            .map(|direction| goal.0 + *direction)
+            .filter(|cell| paths.contains_key(cell))
+            .min_by_key(|cell| paths[cell].0)
+        {
+            Some(cell) => cell,
+            None => return false,
+        };
+        while paths[&cell].0 > MOVERANGE {
+            cell = paths[&cell].1;
+        }
+
+        if let Ok(mut position) = positions.get_mut(mover) {
+            position.0 = cell;
+        }
+        GridPosition(cell).is_adjacent(&goal)
+    }
+}
+
+/// How much of the acting party member's walk is left. While `moving`, the action menu waits.
+#[derive(Default)]
+pub struct GridTurn {
+    pub moving: bool,
+    member: Option<Entity>,
+    steps_left: usize,
+}
+
+impl GridTurn {
+    /// Goes back to walking from the action menu, returns false if no steps are left.
+    pub fn resume(&mut self) -> bool {
+        self.moving = self.member.is_some() && self.steps_left > 0;
+        self.moving
+    }
+}
+
+/// Everything drawn for the grid hangs below this entity.
+#[derive(Component)]
+struct GridSprite;
+
+/// Draws the combatant `owner` on the grid.
+#[derive(Component)]
+struct GridToken {
+    owner: Entity,
+}
+
+#[derive(Component)]
+struct MoveHint;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridTurn>()
+            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(build_grid))
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(place_enemies)
+                .with_system(remove_fallen)
+                .with_system(grid_movement.label("grid_movement").after("combat_log"))
+                .with_system(draw_tokens.after("grid_movement"))
+                .with_system(draw_move_hint.after("grid_movement"))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_grid));
+    }
+}
+
+fn build_grid(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    settings: Res<Settings>,
+    map_query: Query<&Children, With<Map>>,
+    tile_query: Query<(&Transform, &TextureAtlasSprite, Option<&TileCollider>)>,
+    player_query: Query<&Transform, With<Player>>,
+    party_query: Query<(Entity, Option<&Player>), With<PartyMember>>,
+) {
+    if !settings.grid_combat {
+        return;
+    }
+
+    let to_cell = |translation: Vec3| {
+        IVec2::new((translation.x / TILESIZE).round() as i32, (-translation.y / TILESIZE).round() as i32)
+    };
+
+    let mut map = HashMap::new();
+    for children in map_query.iter() {
+        for child in children.iter() {
+            if let Ok((transform, sprite, collider)) = tile_query.get(*child) {
+                map.insert(to_cell(transform.translation), (sprite.index, collider.is_some()));
+            }
+        }
+    }
+
+    // Beyond the edge of the map is as good as a wall.
+    let origin = to_cell(player_query.single().translation) - IVec2::splat(GRIDRADIUS);
+    let size = GRIDRADIUS * 2 + 1;
+    let mut tiles = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            tiles.push(*map.get(&(origin + IVec2::new(x, y))).unwrap_or(&(0, true)));
+        }
+    }
+    let grid = BattleGrid { size, tiles };
+
+    let mut background = TextureAtlasSprite::new(0);
+    background.color = Color::rgb(0.15, 0.15, 0.2);
+    background.custom_size = Some(Vec2::splat(size as f32 * TILESIZE));
+    let mut cells = vec![commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: background,
+            texture_atlas: ascii.0.clone(),
+            ..default()
+        })
+        .id()];
+    for y in 0..size {
+        for x in 0..size {
+            let (index, wall) = grid.tiles[(y * size + x) as usize];
+            let color = if wall { Color::rgb(0.6, 0.6, 0.6) } else { Color::rgb(0.35, 0.35, 0.4) };
+            cells.push(spawn_ascii_sprite(&mut commands, &ascii, index, color, cell_translation(IVec2::new(x, y), 1.0)));
+        }
+    }
+    commands
+        .spawn()
+        .insert(Name::new("BattleGrid"))
+        .insert(GridSprite)
+        .insert(Transform {
+            translation: GRIDCENTER,
+            scale: Vec3::splat(GRIDSCALE),
+            ..default()
+        })
+        .insert(GlobalTransform::default())
+        .push_children(&cells);
+
+    // The player stands where it was on the map, the colleagues gather around it.
+    let center = IVec2::splat(GRIDRADIUS);
+    let mut party: Vec<_> = party_query.iter().collect();
+    party.sort_by_key(|(entity, player)| (player.is_none(), *entity));
+    let paths = grid.paths(center, &[]);
+    let mut taken = Vec::new();
+    for (member, _) in party {
+        let cell = match paths
+            .iter()
+            .filter(|(cell, _)| !taken.contains(*cell))
+            .min_by_key(|(cell, (distance, _))| (*distance, cell.y, cell.x))
+        {
+            Some((cell, _)) => *cell,
+            None => continue,
+        };
+        taken.push(cell);
+        commands.entity(member).insert(GridPosition(cell));
+    }
+
+    commands.insert_resource(grid);
+}
+
+/// Enemies, summoned ones too, show up a few steps away from the player.
+fn place_enemies(
+    mut commands: Commands,
+    grid: Option<Res<BattleGrid>>,
+    enemy_query: Query<Entity, Added<Enemy>>,
+    position_query: Query<&GridPosition>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    let mut taken: Vec<IVec2> = position_query.iter().map(|position| position.0).collect();
+    let paths = grid.paths(IVec2::splat(GRIDRADIUS), &[]);
+    for enemy in enemy_query.iter() {
+        let cell = paths
+            .iter()
+            .filter(|(cell, _)| !taken.contains(*cell))
+            .min_by_key(|(cell, (distance, _))| ((*distance as isize - ENEMYDISTANCE as isize).abs(), cell.y, cell.x));
+        if let Some((cell, _)) = cell {
+            taken.push(*cell);
+            commands.entity(enemy).insert(GridPosition(*cell));
+        }
+    }
+}
+
+/// The fallen and the fled leave the grid and stop blocking cells.
+fn remove_fallen(mut commands: Commands, fallen_query: Query<(Entity, &CombatStats), With<GridPosition>>) {
+    for (entity, stats) in fallen_query.iter() {
+        if stats.health == 0 {
+            commands.entity(entity).remove::<GridPosition>();
+        }
+    }
+}
+
+/// The move actions walk the acting party member, Confirm ends the walk and opens the menu.
+fn grid_movement(
+    mut actions: ResMut<Actions>,
+    grid: Option<Res<BattleGrid>>,
+    turn: Res<CombatTurn>,
+    mut grid_turn: ResMut<GridTurn>,
+    mut position_query: Query<&mut GridPosition>,
+    log: Res<CombatLog>,
+) {
+    let (grid, member) = match (grid, *turn) {
+        (Some(grid), CombatTurn::Party(member)) => (grid, member),
+        _ => {
+            if grid_turn.member.is_some() {
+                *grid_turn = GridTurn::default();
+            }
+            return;
+        }
+    };
+
+    if grid_turn.member != Some(member) {
+        *grid_turn = GridTurn {
+            moving: true,
+            member: Some(member),
+            steps_left: MOVERANGE,
+        };
+    }
+    // Confirm goes to the log while there is something to read.
+    if !grid_turn.moving || !log.is_idle() {
+        return;
+    }
+
+    if actions.just_pressed(Action::Confirm) {
+        actions.clear_just_pressed(Action::Confirm);
+        grid_turn.moving = false;
+        return;
+    }
+
+    let direction = if actions.just_pressed(Action::MoveUp) {
+        IVec2::new(0, -1)
+    } else if actions.just_pressed(Action::MoveDown) {
+        IVec2::Y
+    } else if actions.just_pressed(Action::MoveLeft) {
+        IVec2::new(-1, 0)
+    } else if actions.just_pressed(Action::MoveRight) {
+        IVec2::X
+    } else {
+        return;
+    };
+
+    let target = match position_query.get(member) {
+        Ok(position) => position.0 + direction,
+        Err(_) => return,
+    };
+    if !grid.is_free(target) || position_query.iter().any(|position| position.0 == target) {
+        return;
+    }
+
+    if let Ok(mut position) = position_query.get_mut(member) {
+        position.0 = target;
+    }
+    grid_turn.steps_left -= 1;
+    if grid_turn.steps_left == 0 {
+        grid_turn.moving = false;
+    }
+}
+
+type Occupant<'w, 's> = Query<'w, 's, (Entity, &'static GridPosition, &'static Name, Option<&'static Enemy>, Option<&'static Player>)>;
+type Token<'w, 's> = Query<'w, 's, (Entity, &'static GridToken, &'static mut Transform, &'static mut TextureAtlasSprite)>;
+
+fn draw_tokens(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    grid_query: Query<Entity, With<GridSprite>>,
+    combatant_query: Occupant,
+    mut token_query: Token,
+    turn: Res<CombatTurn>,
+) {
+    let grid = match grid_query.get_single() {
+        Ok(grid) => grid,
+        Err(_) => return,
+    };
+
+    for (token, GridToken { owner }, mut transform, mut sprite) in token_query.iter_mut() {
+        let (_, position, _, enemy, _) = match combatant_query.get(*owner) {
+            Ok(combatant) => combatant,
+            Err(_) => {
+                commands.entity(token).despawn_recursive();
+                continue;
+            }
+        };
+
+        transform.translation = cell_translation(position.0, 2.0);
+        sprite.color = match (enemy, *turn == CombatTurn::Party(*owner)) {
+            (Some(_), _) => Color::rgb(0.9, 0.4, 0.3),
+            (None, true) => Color::rgb(0.95, 0.8, 0.1),
+            (None, false) => Color::rgb(0.4, 0.6, 0.95),
+        };
+    }
+
+    for (owner, position, name, enemy, player) in combatant_query.iter() {
+        if token_query.iter().any(|(_, token, _, _)| token.owner == owner) {
+            continue;
+        }
+
+        // The player keeps its overworld face, everybody else goes by initial.
+        let index = match player {
+            Some(_) => 1,
+            None => name.as_str().chars().next().unwrap_or('?') as usize,
+        };
+        let color = match enemy {
+            Some(_) => Color::rgb(0.9, 0.4, 0.3),
+            None => Color::rgb(0.4, 0.6, 0.95),
+        };
+        let token = spawn_ascii_sprite(&mut commands, &ascii, index, color, cell_translation(position.0, 2.0));
+        commands.entity(token).insert(GridToken { owner });
+        commands.entity(grid).add_child(token);
+    }
+}
+
+fn draw_move_hint(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    grid_turn: Res<GridTurn>,
+    actions: Res<Actions>,
+    hint_query: Query<Entity, With<MoveHint>>,
+) {
+    if !grid_turn.is_changed() {
+        return;
+    }
+
+    for hint in hint_query.iter() {
+        commands.entity(hint).despawn_recursive();
+    }
+    if !grid_turn.moving {
+        return;
+    }
+
+    let position = GRIDCENTER
+        - Vec3::new(
+            (GRIDRADIUS as f32 + 0.5) * TILESIZE * GRIDSCALE,
+            (GRIDRADIUS as f32 + 1.5) * TILESIZE * GRIDSCALE,
+            0.0,
+        );
+    let hint = spawn_ascii_text(
+        &mut commands,
+        &ascii,
+        &format!("MOVE {}  {} - ACT", grid_turn.steps_left, actions.key_name(Action::Confirm)),
+        Color::rgb(0.9, 0.9, 0.9),
+        position,
+    );
+    commands.entity(hint).insert(MoveHint);
+}
+
+type GridEntities = Or<(With<GridSprite>, With<MoveHint>)>;
+
+fn despawn_grid(
+    mut commands: Commands,
+    mut grid_turn: ResMut<GridTurn>,
+    grid_query: Query<Entity, GridEntities>,
+    position_query: Query<Entity, With<GridPosition>>,
+) {
+    commands.remove_resource::<BattleGrid>();
+    *grid_turn = GridTurn::default();
+
+    for entity in grid_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for entity in position_query.iter() {
+        commands.entity(entity).remove::<GridPosition>();
+    }
+}
+
+/// Where `cell` is drawn inside the grid entity, `depth` keeps tokens over the tiles.
+fn cell_translation(cell: IVec2, depth: f32) -> Vec3 {
+    Vec3::new(
+        (cell.x - GRIDRADIUS) as f32 * TILESIZE,
+        (GRIDRADIUS - cell.y) as f32 * TILESIZE,
+        depth,
+    )
+}
@@ -0,0 +1,168 @@
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+
+use crate::{
+    GameState, TILESIZE, PLAYERSIZE,
+    actions::{Action, Actions},
+    ascii::AsciiSheet,
+    combat::{CombatStats, PartyMember},
+    inventory::Inventory,
+    kolleg::{Kolleg, PartyMenu},
+    player::Player,
+    popup::{PopupKind, spawn_banner, spawn_popup},
+    status::StatusEffects,
+    tilemap::HealingTile,
+};
+
+const REGENDISTANCE: f32 = 2.0; // Walked per point of health the party regenerates, twenty tiles.
+const POPUPHEIGHT: f32 = 0.15; // Above the player.
+const HEALCOLOR: Color = Color::rgb(0.3, 0.9, 0.3);
+const REFUSECOLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+
+/// Items that heal when used, with how much.
+const HEALINGITEMS: [(&str, isize); 1] = [("Potion", 5)];
+
+/// How far the player walked since the party last regenerated.
+#[derive(Default)]
+struct Regeneration {
+    last_position: Option<Vec3>,
+    walked: f32,
+}
+
+/// Whether the player is standing in an inn, it only heals once per visit.
+#[derive(Default)]
+struct Resting(bool);
+
+pub struct HealingPlugin;
+
+impl Plugin for HealingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Regeneration>()
+            .init_resource::<Resting>()
+            .add_system_set(SystemSet::on_enter(GameState::Overworld).with_system(reset_regeneration))
+            .add_system_set(SystemSet::on_update(GameState::Overworld)
+                .with_system(regenerate_while_walking)
+                .with_system(rest_at_inn)
+                .with_system(use_healing_item)
+            );
+    }
+}
+
+/// Fights and loading move the player without walking, so distance starts over.
+fn reset_regeneration(mut regeneration: ResMut<Regeneration>) {
+    regeneration.last_position = None;
+}
+
+fn regenerate_while_walking(
+    player_query: Query<&Transform, With<Player>>,
+    mut party_query: Query<&mut CombatStats, With<PartyMember>>,
+    mut regeneration: ResMut<Regeneration>,
+) {
+    let position = player_query.single().translation;
+    if let Some(last_position) = regeneration.last_position {
+        regeneration.walked += position.truncate().distance(last_position.truncate());
+    }
+    regeneration.last_position = Some(position);
+
+    while regeneration.walked >= REGENDISTANCE {
+        regeneration.walked -= REGENDISTANCE;
+        for mut stats in party_query.iter_mut() {
+            // The fallen don't get back up on their own.
+            if stats.health > 0 {
+                stats.heal(1);
+            }
+        }
+    }
+}
+
+/// Everybody the inn takes care of, the player and all colleagues.
+type Roster = Or<(With<Player>, With<Kolleg>)>;
+
+fn rest_at_inn(
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    inn_query: Query<&Transform, (With<HealingTile>, Without<Player>)>,
+    mut roster_query: Query<(&mut CombatStats, &mut StatusEffects), Roster>,
+    camera_query: Query<Entity, With<Camera>>,
+    ascii: Res<AsciiSheet>,
+    mut resting: ResMut<Resting>,
+) {
+    let position = player_query.single().translation;
+    let on_inn = inn_query.iter().any(|inn| {
+        collide(position, Vec2::splat(TILESIZE * PLAYERSIZE), inn.translation, Vec2::splat(TILESIZE)).is_some()
+    });
+
+    if on_inn && !resting.0 {
+        // The inn looks after everyone on the roster, benched colleagues too.
+        for (mut stats, mut effects) in roster_query.iter_mut() {
+            let max_health = stats.max_health;
+            stats.heal(max_health);
+            stats.mana = stats.max_mana;
+            effects.0.clear();
+        }
+        spawn_banner(
+            &mut commands,
+            &ascii,
+            camera_query.single(),
+            "You rest at the inn.",
+            HEALCOLOR,
+        );
+    }
+    resting.0 = on_inn;
+}
+
+/// UseItem uses the first healing item in the bag on whoever in the party is hurt the most.
+fn use_healing_item(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_query: Query<(&mut Inventory, &Transform), With<Player>>,
+    mut party_query: Query<(&Name, &mut CombatStats), With<PartyMember>>,
+    party_menu: Res<PartyMenu>,
+    camera_query: Query<Entity, With<Camera>>,
+    ascii: Res<AsciiSheet>,
+) {
+    if !actions.just_pressed(Action::UseItem) || party_menu.open {
+        return;
+    }
+    let camera = camera_query.single();
+
+    let (mut inventory, transform) = player_query.single_mut();
+    let (item, power) = match HEALINGITEMS
+        .iter()
+        .find(|(item, _)| inventory.items.iter().any(|(owned, _)| owned == item))
+    {
+        Some(&healing) => healing,
+        None => {
+            spawn_banner(&mut commands, &ascii, camera, "No healing items left.", REFUSECOLOR);
+            return;
+        }
+    };
+
+    let patient = party_query
+        .iter_mut()
+        .filter(|(_, stats)| stats.health > 0 && stats.health < stats.max_health)
+        .min_by_key(|(_, stats)| stats.health * 100 / stats.max_health.max(1));
+    let (name, mut stats) = match patient {
+        Some(patient) => patient,
+        None => {
+            spawn_banner(&mut commands, &ascii, camera, "Nobody needs healing.", REFUSECOLOR);
+            return;
+        }
+    };
+
+    inventory.remove_item(item);
+    let healed = stats.heal(power);
+    spawn_banner(
+        &mut commands,
+        &ascii,
+        camera,
+        &format!("{} uses a {}: +{} HP", name.as_str(), item, healed),
+        HEALCOLOR,
+    );
+    spawn_popup(
+        &mut commands,
+        &ascii,
+        PopupKind::Heal,
+        healed,
+        transform.translation + Vec3::new(0.0, POPUPHEIGHT, 50.0),
+    );
+}
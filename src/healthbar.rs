@@ -0,0 +1,350 @@
+use bevy::{prelude::*, math::{const_vec2, const_vec3}};
+
+use crate::{
+    GameState, TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_sprite, spawn_ascii_text},
+    combat::{AtbGauge, CombatStats, Fled, PartyMember},
+    player::Player,
+    settings::{CombatMode, Settings},
+    status::{StatusEffects, StatusKind},
+};
+
+const BARHEIGHT: f32 = 0.03;
+const ENEMYBARWIDTH: f32 = 0.6;
+const PARTYBARWIDTH: f32 = 0.7;
+const BARSPEED: f32 = 4.0; // How fast the displayed health catches up with the real one.
+
+// The party's panels fill a two by two grid from the bottom left, the player's first.
+const PARTYPANELPOSITION: Vec3 = const_vec3!([-1.35, -0.85, 800.0]);
+const PANELSPACING: Vec2 = const_vec2!([0.8, 0.45]);
+
+#[derive(Component)]
+pub struct HealthBar {
+    owner: Entity,
+    displayed: f32,
+    width: f32,
+}
+
+impl HealthBar {
+    /// The combatant whose health this bar shows.
+    pub fn owner(&self) -> Entity {
+        self.owner
+    }
+}
+
+#[derive(Component)]
+struct HealthBarFill;
+
+/// The thin bar under a health bar showing how full the owner's `AtbGauge` is.
+#[derive(Component)]
+struct GaugeFill;
+
+/// A party member's health bar with its texts, it stands in for the hidden party in combat.
+#[derive(Component)]
+pub struct PartyPanel;
+
+#[derive(Component)]
+struct HealthText;
+
+#[derive(Component)]
+struct ManaText;
+
+/// The status effects currently drawn next to a health bar.
+#[derive(Component, Default)]
+struct StatusIcons(Vec<StatusKind>);
+
+#[derive(Component)]
+struct StatusIcon;
+
+pub struct HealthBarPlugin;
+
+impl Plugin for HealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Combat).with_system(spawn_party_panels))
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(animate_health_bars)
+                .with_system(update_status_icons)
+                .with_system(animate_atb_gauges)
+                .with_system(remove_fled_bars)
+            );
+    }
+}
+
+/// Spawns a bar centered on `translation` that follows the health of `owner`.
+pub fn spawn_health_bar(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    owner: Entity,
+    stats: &CombatStats,
+    translation: Vec3,
+) -> Entity {
+    create_health_bar(commands, ascii, owner, stats, ENEMYBARWIDTH, translation)
+}
+
+fn create_health_bar(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    owner: Entity,
+    stats: &CombatStats,
+    width: f32,
+    translation: Vec3,
+) -> Entity {
+    let displayed = health_fraction(stats.health as f32, stats);
+
+    let background = spawn_bar_sprite(commands, ascii, Color::rgb(0.2, 0.2, 0.2), width, Vec3::ZERO);
+    let fill = spawn_bar_sprite(
+        commands,
+        ascii,
+        bar_color(displayed),
+        width * displayed,
+        Vec3::new(-width * (1.0 - displayed) / 2.0, 0.0, 1.0),
+    );
+    commands.entity(fill).insert(HealthBarFill);
+
+    let gauge = spawn_bar_sprite(
+        commands,
+        ascii,
+        Color::rgb(0.3, 0.7, 0.95),
+        0.0,
+        Vec3::new(-width / 2.0, -BARHEIGHT * 1.5, 1.0),
+    );
+    commands.entity(gauge).insert(GaugeFill);
+
+    commands
+        .spawn()
+        .insert(Name::new("HealthBar"))
+        .insert(HealthBar {
+            owner,
+            displayed: stats.health as f32,
+            width,
+        })
+        .insert(StatusIcons::default())
+        .insert(Transform {
+            translation,
+            ..default()
+        })
+        .insert(GlobalTransform::default())
+        .push_children(&[background, fill, gauge])
+        .id()
+}
+
+fn spawn_bar_sprite(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    color: Color,
+    width: f32,
+    translation: Vec3,
+) -> Entity {
+    let mut sprite = TextureAtlasSprite::new(0);
+    sprite.color = color;
+    sprite.custom_size = Some(Vec2::new(width, BARHEIGHT));
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite,
+            texture_atlas: ascii.0.clone(),
+            transform: Transform {
+                translation,
+                ..default()
+            },
+            ..default()
+        })
+        .id()
+}
+
+fn spawn_party_panels(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    party_query: Query<(Entity, &Name, &CombatStats, Option<&Player>), With<PartyMember>>,
+) {
+    let mut party: Vec<_> = party_query.iter().collect();
+    party.sort_by_key(|(entity, _, _, player)| (player.is_none(), *entity));
+
+    for (i, (member, name, stats, _)) in party.into_iter().enumerate() {
+        let position = PARTYPANELPOSITION
+            + Vec3::new((i % 2) as f32 * PANELSPACING.x, (i / 2) as f32 * PANELSPACING.y, 0.0);
+        let bar = create_health_bar(&mut commands, &ascii, member, stats, PARTYBARWIDTH, position);
+
+        let text = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            &health_text(stats.health, stats.max_health),
+            Color::rgb(0.9, 0.9, 0.9),
+            Vec3::new(-PARTYBARWIDTH / 2.0 + TILESIZE / 2.0, TILESIZE, 0.0),
+        );
+        commands.entity(text).insert(HealthText);
+
+        let mana = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            &mana_text(stats.mana, stats.max_mana),
+            Color::rgb(0.4, 0.6, 0.95),
+            Vec3::new(-PARTYBARWIDTH / 2.0 + TILESIZE / 2.0, 2.0 * TILESIZE, 0.0),
+        );
+        commands.entity(mana).insert(ManaText);
+
+        let title = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            name.as_str(),
+            Color::rgb(0.95, 0.8, 0.1),
+            Vec3::new(-PARTYBARWIDTH / 2.0 + TILESIZE / 2.0, 3.0 * TILESIZE, 0.0),
+        );
+
+        commands
+            .entity(bar)
+            .insert(Name::new("PartyStatusPanel"))
+            .insert(PartyPanel)
+            .push_children(&[text, mana, title]);
+    }
+}
+
+fn animate_health_bars(
+    mut bar_query: Query<(&mut HealthBar, &Children)>,
+    stats_query: Query<&CombatStats>,
+    mut fill_query: Query<(&mut TextureAtlasSprite, &mut Transform), With<HealthBarFill>>,
+    text_query: Query<&Children, With<HealthText>>,
+    mana_query: Query<&Children, With<ManaText>>,
+    mut char_query: Query<&mut TextureAtlasSprite, Without<HealthBarFill>>,
+    time: Res<Time>,
+) {
+    for (mut bar, children) in bar_query.iter_mut() {
+        let stats = match stats_query.get(bar.owner) {
+            Ok(stats) => stats,
+            Err(_) => continue,
+        };
+
+        // Ease the displayed health towards the real value.
+        let target = stats.health as f32;
+        let step = (BARSPEED * time.delta_seconds()).min(1.0);
+        bar.displayed += (target - bar.displayed) * step;
+        if (target - bar.displayed).abs() < 0.01 {
+            bar.displayed = target;
+        }
+
+        let fraction = health_fraction(bar.displayed, stats);
+
+        for child in children.iter() {
+            if let Ok((mut sprite, mut transform)) = fill_query.get_mut(*child) {
+                sprite.custom_size = Some(Vec2::new(bar.width * fraction, BARHEIGHT));
+                sprite.color = bar_color(fraction);
+                transform.translation.x = -bar.width * (1.0 - fraction) / 2.0;
+            }
+
+            let text = if let Ok(characters) = text_query.get(*child) {
+                Some((characters, health_text(bar.displayed.round() as isize, stats.max_health)))
+            } else if let Ok(characters) = mana_query.get(*child) {
+                Some((characters, mana_text(stats.mana, stats.max_mana)))
+            } else {
+                None
+            };
+
+            if let Some((characters, text)) = text {
+                for (character, index) in characters.iter().zip(text.chars()) {
+                    if let Ok(mut sprite) = char_query.get_mut(*character) {
+                        sprite.index = index as usize;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn animate_atb_gauges(
+    bar_query: Query<(&HealthBar, &Children)>,
+    gauge_query: Query<&AtbGauge>,
+    mut fill_query: Query<(&mut TextureAtlasSprite, &mut Transform), With<GaugeFill>>,
+    settings: Res<Settings>,
+) {
+    for (bar, children) in bar_query.iter() {
+        // Turn based fights have no gauges, so the bar stays empty.
+        let fraction = match (settings.combat_mode, gauge_query.get(bar.owner)) {
+            (CombatMode::Atb, Ok(gauge)) => gauge.0.clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+
+        for child in children.iter() {
+            if let Ok((mut sprite, mut transform)) = fill_query.get_mut(*child) {
+                sprite.custom_size = Some(Vec2::new(bar.width * fraction, BARHEIGHT / 2.0));
+                transform.translation.x = -bar.width * (1.0 - fraction) / 2.0;
+            }
+        }
+    }
+}
+
+fn remove_fled_bars(
+    mut commands: Commands,
+    bar_query: Query<(Entity, &HealthBar)>,
+    fled_query: Query<(), With<Fled>>,
+) {
+    for (entity, bar) in bar_query.iter() {
+        if fled_query.get(bar.owner).is_ok() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn update_status_icons(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    mut bar_query: Query<(Entity, &HealthBar, &mut StatusIcons, &Children)>,
+    effects_query: Query<&StatusEffects>,
+    icon_query: Query<Entity, With<StatusIcon>>,
+) {
+    for (entity, bar, mut icons, children) in bar_query.iter_mut() {
+        let effects = match effects_query.get(bar.owner) {
+            Ok(effects) => effects,
+            Err(_) => continue,
+        };
+
+        let active: Vec<StatusKind> = effects.0.iter().map(|effect| effect.kind).collect();
+        if active == icons.0 {
+            continue;
+        }
+
+        for child in children.iter() {
+            if let Ok(icon) = icon_query.get(*child) {
+                commands.entity(icon).despawn_recursive();
+            }
+        }
+
+        for (i, kind) in active.iter().enumerate() {
+            let icon = spawn_ascii_sprite(
+                &mut commands,
+                &ascii,
+                kind.icon(),
+                kind.color(),
+                Vec3::new(bar.width / 2.0 + TILESIZE * (i as f32 + 1.0), 0.0, 1.0),
+            );
+            commands.entity(icon).insert(StatusIcon);
+            commands.entity(entity).add_child(icon);
+        }
+        icons.0 = active;
+    }
+}
+
+fn health_fraction(health: f32, stats: &CombatStats) -> f32 {
+    if stats.max_health <= 0 {
+        return 0.0;
+    }
+    (health / stats.max_health as f32).clamp(0.0, 1.0)
+}
+
+// Fixed width, so the characters can be swapped in place.
+fn health_text(health: isize, max_health: isize) -> String {
+    format!("HP {:>3}/{:>3}", health, max_health)
+}
+
+fn mana_text(mana: isize, max_mana: isize) -> String {
+    format!("MP {:>3}/{:>3}", mana, max_mana)
+}
+
+fn bar_color(fraction: f32) -> Color {
+    if fraction > 0.5 {
+        Color::rgb(0.2, 0.8, 0.2)
+    } else if fraction > 0.2 {
+        Color::rgb(0.9, 0.8, 0.2)
+    } else {
+        Color::rgb(0.9, 0.2, 0.2)
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+/// The player's money and the items they carry, by name and count.
+#[derive(Component, Clone, Default)]
+pub struct Inventory {
+    pub gold: usize,
+    pub items: Vec<(String, usize)>,
+}
+
+impl Inventory {
+    pub fn add_item(&mut self, name: &str) {
+        match self.items.iter_mut().find(|(item, _)| item == name) {
+            Some((_, count)) => *count += 1,
+            None => self.items.push((name.to_string(), 1)),
+        }
+    }
+
+    /// Takes one `name` out of the bag, returns false if there was none.
+    pub fn remove_item(&mut self, name: &str) -> bool {
+        let index = match self.items.iter().position(|(item, _)| item == name) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.items[index].1 -= 1;
+        if self.items[index].1 == 0 {
+            self.items.remove(index);
+        }
+        true
+    }
+}
@@ -1,4 +1,18 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ecs::system::SystemParam};
+
+use crate::{
+    GameState, TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_text},
+    combat::{AtbGauge, CombatStats, EnemyType, PartyMember},
+    combat_log::CombatLog,
+    popup::spawn_banner,
+    skills::KnownSkills,
+    actions::{Action, Actions},
+    status::StatusEffects,
+};
+
+pub const MAXACTIVE: usize = 3; // Colleagues fighting next to the player.
+const MENUWIDTH: f32 = 3.6;
 
 pub struct KollegPlugin;
 
@@ -6,9 +20,38 @@ pub struct RehuHandle(pub Handle<TextureAtlas>);
 pub struct ImiHandle(pub Handle<TextureAtlas>);
 pub struct MibiHandle(pub Handle<TextureAtlas>);
 
+/// A defeated enemy that joined the player, it keeps its own `CombatStats` between fights.
+#[derive(Component)]
+pub struct Kolleg {
+    pub enemy_type: EnemyType,
+    pub active: bool, // Only active colleagues take part in fights.
+}
+
+/// Sent when the player accepts a defeated enemy's offer to join.
+pub struct RecruitEvent(pub EnemyType);
+
+/// The overworld menu for choosing who is in the active party.
+#[derive(Default)]
+pub struct PartyMenu {
+    pub open: bool,
+    pub selected: usize,
+}
+
+#[derive(Component)]
+struct PartyMenuLine;
+
 impl Plugin for KollegPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system_to_stage(StartupStage::PreStartup, load_kolleg);
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_kolleg)
+            .init_resource::<PartyMenu>()
+            .add_event::<RecruitEvent>()
+            .add_system(recruit)
+            .add_system(sync_party)
+            .add_system_set(SystemSet::on_update(GameState::Overworld)
+                .with_system(party_menu_input)
+                .with_system(draw_party_menu.after(party_menu_input))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Overworld).with_system(close_party_menu));
     }
 }
 
@@ -17,13 +60,13 @@ fn load_kolleg(
     assets: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>
 ) {
+    // Each picture is a single frame the size of the whole image.
     let image = assets.load("rehu.png");
-    let atlas = TextureAtlas::from_grid_with_padding(
+    let atlas = TextureAtlas::from_grid(
         image,
-        Vec2::splat(9.0),
+        Vec2::new(851.0, 832.0),
         1,
         1,
-        Vec2::splat(2.0)
     );
 
     let atlas_handle = texture_atlases.add(atlas);
@@ -31,12 +74,11 @@ fn load_kolleg(
     commands.insert_resource(RehuHandle(atlas_handle));
 
     let image = assets.load("imi.png");
-    let atlas = TextureAtlas::from_grid_with_padding(
+    let atlas = TextureAtlas::from_grid(
         image,
-        Vec2::splat(9.0),
+        Vec2::new(1600.0, 1200.0),
         1,
         1,
-        Vec2::splat(2.0)
     );
 
     let atlas_handle = texture_atlases.add(atlas);
@@ -44,15 +86,243 @@ fn load_kolleg(
     commands.insert_resource(ImiHandle(atlas_handle));
 
     let image = assets.load("mibi.png");
-    let atlas = TextureAtlas::from_grid_with_padding(
+    let atlas = TextureAtlas::from_grid(
         image,
-        Vec2::splat(9.0),
+        Vec2::new(1200.0, 1600.0),
         1,
         1,
-        Vec2::splat(2.0)
     );
 
     let atlas_handle = texture_atlases.add(atlas);
 
     commands.insert_resource(MibiHandle(atlas_handle));
-}
\ No newline at end of file
+}
+
+/// Adds a colleague to the roster, it joins the active party if there is room.
+pub fn spawn_kolleg(commands: &mut Commands, enemy_type: EnemyType, stats: CombatStats, active: bool) -> Entity {
+    commands
+        .spawn()
+        .insert(Name::new(enemy_type.name()))
+        .insert(Kolleg { enemy_type, active })
+        .insert(stats)
+        .insert(StatusEffects::default())
+        .insert(KnownSkills::default())
+        .insert(enemy_type.affinities())
+        .insert(AtbGauge::default())
+        .insert(Transform::default())
+        .insert(GlobalTransform::default())
+        .id()
+}
+
+fn recruit(
+    mut commands: Commands,
+    mut recruit_event: EventReader<RecruitEvent>,
+    kolleg_query: Query<&Kolleg>,
+    mut log: ResMut<CombatLog>,
+) {
+    let active = kolleg_query.iter().filter(|kolleg| kolleg.active).count();
+
+    for (i, event) in recruit_event.iter().enumerate() {
+        // Recruits join at full health, whatever the fight left them with.
+        let mut stats = event.0.stats();
+        stats.heal(stats.max_health);
+
+        spawn_kolleg(&mut commands, event.0, stats, active + i < MAXACTIVE);
+        log.push(format!("{} joined the party!", event.0.name()));
+    }
+}
+
+/// Combat only looks at the `PartyMember` marker, so it follows the roster.
+fn sync_party(mut commands: Commands, kolleg_query: Query<(Entity, &Kolleg), Changed<Kolleg>>) {
+    for (entity, kolleg) in kolleg_query.iter() {
+        if kolleg.active {
+            commands.entity(entity).insert(PartyMember);
+        } else {
+            commands.entity(entity).remove::<PartyMember>();
+        }
+    }
+}
+
+fn party_menu_input(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut menu: ResMut<PartyMenu>,
+    mut kolleg_query: Query<&mut Kolleg>,
+    ascii: Res<AsciiSheet>,
+    camera_query: Query<Entity, With<Camera>>,
+) {
+    if actions.just_pressed(Action::Menu) || (menu.open && actions.just_pressed(Action::Cancel)) {
+        menu.open = !menu.open;
+        menu.selected = 0;
+    }
+    if !menu.open {
+        return;
+    }
+
+    let entries = kolleg_query.iter().count();
+    if entries == 0 {
+        return;
+    }
+
+    if actions.just_pressed(Action::MoveUp) {
+        menu.selected = (menu.selected + entries - 1) % entries;
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        menu.selected = (menu.selected + 1) % entries;
+    }
+
+    if actions.just_pressed(Action::Confirm) {
+        let active = kolleg_query.iter().filter(|kolleg| kolleg.active).count();
+        if let Some(mut kolleg) = kolleg_query.iter_mut().nth(menu.selected) {
+            if kolleg.active {
+                kolleg.active = false;
+            } else if active < MAXACTIVE {
+                kolleg.active = true;
+            } else {
+                spawn_banner(&mut commands, &ascii, camera_query.single(), "The party is full.", Color::rgb(0.6, 0.6, 0.6));
+            }
+        }
+    }
+}
+
+type KollegChanged = Or<(Changed<Kolleg>, Changed<CombatStats>)>;
+
+/// The roster as the party menu shows it.
+#[derive(SystemParam)]
+struct RosterState<'w, 's> {
+    menu: Res<'w, PartyMenu>,
+    kolleg_query: Query<'w, 's, (&'static Kolleg, &'static CombatStats)>,
+    changed_query: Query<'w, 's, (), KollegChanged>,
+}
+
+impl RosterState<'_, '_> {
+    fn is_changed(&self) -> bool {
+        self.menu.is_changed() || !self.changed_query.is_empty()
+    }
+}
+
+/// The sprite sheets of every enemy type that can join.
+#[derive(SystemParam)]
+struct Portraits<'w, 's> {
+    rehu: Res<'w, RehuHandle>,
+    imi: Res<'w, ImiHandle>,
+    mibi: Res<'w, MibiHandle>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl Portraits<'_, '_> {
+    fn atlas(&self, enemy_type: EnemyType) -> Handle<TextureAtlas> {
+        match enemy_type {
+            EnemyType::Rehu | EnemyType::RehuKing => self.rehu.0.clone(),
+            EnemyType::Imi => self.imi.0.clone(),
+            EnemyType::Mibi => self.mibi.0.clone(),
+        }
+    }
+}
+
+fn draw_party_menu(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    line_query: Query<Entity, With<PartyMenuLine>>,
+    camera_query: Query<&Transform, With<Camera>>,
+    roster: RosterState,
+    portraits: Portraits,
+    actions: Res<Actions>,
+) {
+    if !roster.is_changed() {
+        return;
+    }
+    let RosterState { menu, kolleg_query, .. } = roster;
+
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+    if !menu.open {
+        return;
+    }
+
+    let center = camera_query.single().translation;
+    let mut lines = vec![(
+        format!("PARTY {}/{}", kolleg_query.iter().filter(|(kolleg, _)| kolleg.active).count(), MAXACTIVE),
+        Color::rgb(0.95, 0.8, 0.1),
+        None,
+    )];
+    for (i, (kolleg, stats)) in kolleg_query.iter().enumerate() {
+        let cursor = if i == menu.selected { '>' } else { ' ' };
+        let mark = if kolleg.active { '*' } else { ' ' };
+        let color = if kolleg.active { Color::rgb(0.9, 0.9, 0.9) } else { Color::rgb(0.5, 0.5, 0.5) };
+        lines.push((
+            format!(
+                "{}{} {:<9} HP{:>3}/{:<3} ATK{:>2} DEF{:>2}",
+                cursor, mark, kolleg.enemy_type.name(), stats.health, stats.max_health, stats.attack, stats.defense
+            ),
+            color,
+            Some(kolleg.enemy_type),
+        ));
+    }
+    if lines.len() == 1 {
+        lines.push(("No colleagues yet".to_string(), Color::rgb(0.5, 0.5, 0.5), None));
+    }
+    lines.push((
+        format!("{} - TOGGLE  {} - CLOSE", actions.key_name(Action::Confirm), actions.key_name(Action::Menu)),
+        Color::rgb(0.9, 0.9, 0.9),
+        None,
+    ));
+
+    let top = center.y + lines.len() as f32 * TILESIZE * 1.2 / 2.0;
+    let left = center.x - MENUWIDTH / 2.0 + TILESIZE;
+
+    let mut background = TextureAtlasSprite::new(0);
+    background.color = Color::rgba(0.05, 0.05, 0.1, 0.9);
+    background.custom_size = Some(Vec2::new(MENUWIDTH + 0.4, (lines.len() as f32 + 1.0) * TILESIZE * 1.2));
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: background,
+            texture_atlas: ascii.0.clone(),
+            transform: Transform {
+                translation: Vec3::new(center.x, center.y, 940.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("PartyMenu"))
+        .insert(PartyMenuLine);
+
+    for (i, (text, color, enemy_type)) in lines.iter().enumerate() {
+        let left_center = Vec3::new(left, top - (i as f32 + 0.5) * TILESIZE * 1.2, 950.0);
+        let line = spawn_ascii_text(&mut commands, &ascii, text, *color, left_center);
+        commands.entity(line).insert(PartyMenuLine);
+
+        // A small picture of the colleague in front of its line.
+        if let Some(enemy_type) = enemy_type {
+            let atlas = portraits.atlas(*enemy_type);
+            let mut sprite = TextureAtlasSprite::new(0);
+            sprite.custom_size = Some(Vec2::splat(TILESIZE));
+
+            let portrait = commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite,
+                    texture_atlas: atlas,
+                    transform: Transform {
+                        translation: Vec3::new(left - TILESIZE * 1.5, left_center.y, 950.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .id();
+            commands.entity(portrait).insert(PartyMenuLine);
+        }
+    }
+}
+
+fn close_party_menu(
+    mut commands: Commands,
+    mut menu: ResMut<PartyMenu>,
+    line_query: Query<Entity, With<PartyMenuLine>>,
+) {
+    menu.open = false;
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+}
@@ -0,0 +1,44 @@
+//! The game itself, shared by the game binary and the combat simulator.
+#![allow(clippy::redundant_field_names)]
+
+pub const TILESIZE: f32 = 0.1;
+pub const PLAYERSPEED: f32 = 2.5;
+pub const PLAYERSIZE: f32 = 0.9;
+
+pub mod player;
+pub mod debug;
+pub mod ascii;
+pub mod tilemap;
+pub mod combat;
+pub mod fadeout;
+pub mod kolleg;
+pub mod healthbar;
+pub mod popup;
+pub mod experience;
+pub mod save;
+pub mod gameover;
+pub mod status;
+pub mod skills;
+pub mod combat_menu;
+pub mod element;
+pub mod damage;
+pub mod combat_log;
+pub mod settings;
+pub mod inventory;
+pub mod loot;
+pub mod ai;
+pub mod boss;
+pub mod animation;
+pub mod timeline;
+pub mod terrain;
+pub mod timing;
+pub mod healing;
+pub mod grid;
+pub mod actions;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum GameState {
+    Overworld,
+    Combat,
+    GameOver,
+}
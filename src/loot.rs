@@ -0,0 +1,267 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+
+use bevy::{prelude::*, ecs::system::SystemParam};
+use rand::Rng;
+
+use crate::{
+    GameState, TILESIZE,
+    actions::{Action, Actions},
+    ascii::{AsciiSheet, spawn_ascii_text},
+    combat::{CombatStats, CombatTurn, EnemyDefeated, EnemyType},
+    combat_log::CombatLog,
+    damage::CombatRng,
+    experience::ExperienceEvent,
+    fadeout::Fader,
+    animation::CombatAnimation,
+    inventory::Inventory,
+    kolleg::RecruitEvent,
+    player::Player,
+};
+
+/// What one enemy may drop when defeated.
+pub struct LootEntry {
+    pub enemy_type: EnemyType,
+    pub gold: (usize, usize), // Inclusive range.
+    pub drops: Vec<(String, f64)>, // Item name and the chance it drops.
+}
+
+/// Every enemy's drops, as loaded from `assets/loot.txt`.
+pub struct LootTable(pub Vec<LootEntry>);
+
+impl LootTable {
+    /// Rolls the gold and items for one defeated enemy.
+    pub fn roll(&self, enemy_type: EnemyType, rng: &mut impl Rng) -> (usize, Vec<String>) {
+        let entry = match self.0.iter().find(|entry| entry.enemy_type == enemy_type) {
+            Some(entry) => entry,
+            None => return (0, Vec::new()),
+        };
+
+        let gold = rng.gen_range(entry.gold.0..=entry.gold.1);
+        let items = entry.drops
+            .iter()
+            .filter(|(_, chance)| rng.gen_bool(*chance))
+            .map(|(item, _)| item.clone())
+            .collect();
+        (gold, items)
+    }
+}
+
+/// Everything earned in the current fight, listed on the victory screen.
+#[derive(Default)]
+struct Rewards {
+    defeated: usize, // Enemies that ran away are not counted.
+    experience: usize,
+    gold: usize,
+    items: Vec<String>,
+    recruit: Option<EnemyType>, // At most one enemy offers to join per fight.
+}
+
+#[derive(Component)]
+struct VictoryScreen;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rewards>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, load_loot_table)
+            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(reset_rewards))
+            .add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(collect_rewards)
+                .with_system(spawn_victory_screen.after(collect_rewards))
+                .with_system(victory_input)
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_victory_screen));
+    }
+}
+
+fn load_loot_table(mut commands: Commands) {
+    let file = File::open("assets/loot.txt").expect("No loot file found");
+    let mut entries = Vec::new();
+
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        entries.push(parse_entry(line).unwrap_or_else(|| panic!("Invalid loot entry: {}", line)));
+    }
+
+    commands.insert_resource(LootTable(entries));
+}
+
+fn parse_entry(line: &str) -> Option<LootEntry> {
+    let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+    if fields.len() != 3 {
+        return None;
+    }
+
+    let (min, max) = fields[1].split_once('-')?;
+    let gold = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+    if gold.0 > gold.1 {
+        return None;
+    }
+
+    let mut drops = Vec::new();
+    for drop in fields[2].split(',').map(|drop| drop.trim()).filter(|drop| !drop.is_empty()) {
+        let (item, chance) = drop.split_once(':')?;
+        let chance: f64 = chance.trim().parse().ok()?;
+        if !(0.0..=1.0).contains(&chance) {
+            return None;
+        }
+        drops.push((item.trim().to_string(), chance));
+    }
+
+    Some(LootEntry {
+        enemy_type: EnemyType::parse(fields[0])?,
+        gold,
+        drops,
+    })
+}
+
+fn reset_rewards(mut rewards: ResMut<Rewards>) {
+    *rewards = Rewards::default();
+}
+
+fn collect_rewards(
+    mut experience_event: EventReader<ExperienceEvent>,
+    mut defeated_event: EventReader<EnemyDefeated>,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    table: Res<LootTable>,
+    mut rng: ResMut<CombatRng>,
+    mut rewards: ResMut<Rewards>,
+) {
+    for event in experience_event.iter() {
+        rewards.experience += event.0;
+    }
+
+    for event in defeated_event.iter() {
+        let mut inventory = player_query.single_mut();
+        let (gold, items) = table.roll(event.0, &mut rng.0);
+
+        rewards.defeated += 1;
+        if rewards.recruit.is_none() && rng.0.gen_bool(event.0.recruit_chance()) {
+            rewards.recruit = Some(event.0);
+        }
+        inventory.gold += gold;
+        rewards.gold += gold;
+        for item in items {
+            inventory.add_item(&item);
+            rewards.items.push(item);
+        }
+    }
+}
+
+/// Whether the fight was won and has fully played out, with no victory screen up yet.
+#[derive(SystemParam)]
+struct VictoryPending<'w, 's> {
+    log: Res<'w, CombatLog>,
+    turn: Res<'w, CombatTurn>,
+    player_query: Query<'w, 's, &'static CombatStats, With<Player>>,
+    screen_query: Query<'w, 's, (), With<VictoryScreen>>,
+    animation_query: Query<'w, 's, (), With<CombatAnimation>>,
+}
+
+impl VictoryPending<'_, '_> {
+    fn is_pending(&self) -> bool {
+        // Wait until the last hit has been read and the last enemy has faded.
+        if *self.turn != CombatTurn::Over || !self.log.is_idle() || !self.screen_query.is_empty() {
+            return false;
+        }
+        self.animation_query.is_empty() && self.player_query.single().health > 0
+    }
+}
+
+fn spawn_victory_screen(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    rewards: Res<Rewards>,
+    pending: VictoryPending,
+    camera_query: Query<&Transform, With<Camera>>,
+    actions: Res<Actions>,
+) {
+    if !pending.is_pending() {
+        return;
+    }
+
+    let title = if rewards.defeated > 0 { "VICTORY!" } else { "THEY GOT AWAY" };
+    let white = Color::rgb(0.9, 0.9, 0.9);
+    let mut lines = vec![
+        (title.to_string(), Color::rgb(0.95, 0.8, 0.1)),
+        (format!("EXP  +{}", rewards.experience), white),
+        (format!("GOLD +{}", rewards.gold), white),
+    ];
+    if rewards.items.is_empty() {
+        lines.push(("No items".to_string(), Color::rgb(0.5, 0.5, 0.5)));
+    }
+    for item in rewards.items.iter() {
+        lines.push((item.clone(), Color::rgb(0.4, 0.8, 0.95)));
+    }
+    match rewards.recruit {
+        Some(enemy_type) => {
+            lines.push((format!("{} wants to join you!", enemy_type.name()), Color::rgb(0.3, 0.9, 0.3)));
+            lines.push((
+                format!("{} - ACCEPT  {} - DECLINE", actions.key_name(Action::Accept), actions.key_name(Action::Confirm)),
+                white,
+            ));
+        }
+        None => lines.push((format!("{} - CONTINUE", actions.key_name(Action::Confirm)), white)),
+    }
+
+    let center = camera_query.single().translation;
+    let top = center.y + lines.len() as f32 * TILESIZE * 1.2 / 2.0;
+
+    let mut sprite = TextureAtlasSprite::new(0);
+    sprite.color = Color::rgba(0.05, 0.05, 0.1, 0.9);
+    sprite.custom_size = Some(Vec2::new(2.0, (lines.len() as f32 + 1.0) * TILESIZE * 1.2));
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite,
+            texture_atlas: ascii.0.clone(),
+            transform: Transform {
+                translation: Vec3::new(center.x, center.y, 890.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("VictoryScreen"))
+        .insert(VictoryScreen);
+
+    for (i, (text, color)) in lines.iter().enumerate() {
+        let left_center = Vec3::new(
+            center.x - (text.len() as f32 - 1.0) * TILESIZE / 2.0,
+            top - (i as f32 + 0.5) * TILESIZE * 1.2,
+            900.0,
+        );
+        let line = spawn_ascii_text(&mut commands, &ascii, text, *color, left_center);
+        commands.entity(line).insert(VictoryScreen);
+    }
+}
+
+fn victory_input(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    screen_query: Query<(), With<VictoryScreen>>,
+    rewards: Res<Rewards>,
+    mut recruit_event: EventWriter<RecruitEvent>,
+    mut fader: Fader,
+) {
+    if screen_query.is_empty() || fader.is_fading() {
+        return;
+    }
+
+    let accept = actions.just_pressed(Action::Accept) && rewards.recruit.is_some();
+    if accept {
+        recruit_event.send(RecruitEvent(rewards.recruit.unwrap()));
+    }
+
+    if accept || actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Cancel) {
+        fader.fade_to(&mut commands, GameState::Overworld);
+    }
+}
+
+fn despawn_victory_screen(mut commands: Commands, screen_query: Query<Entity, With<VictoryScreen>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -6,38 +6,41 @@ use bevy::{
     window::PresentMode,
 };
 
+use bevy_tutorial::{
+    GameState,
+    player::PlayerPlugin,
+    debug::DebugPlugin,
+    ascii::AsciiPlugin,
+    tilemap::TileMapPlugin,
+    combat::CombatPlugin,
+    fadeout::FadeoutPlugin,
+    kolleg::KollegPlugin,
+    healthbar::HealthBarPlugin,
+    popup::PopupPlugin,
+    experience::ExperiencePlugin,
+    save::SavePlugin,
+    gameover::GameOverPlugin,
+    status::StatusPlugin,
+    skills::SkillsPlugin,
+    combat_menu::CombatMenuPlugin,
+    damage::DamagePlugin,
+    combat_log::CombatLogPlugin,
+    settings::SettingsPlugin,
+    loot::LootPlugin,
+    boss::BossPlugin,
+    animation::AnimationPlugin,
+    timeline::TimelinePlugin,
+    terrain::TerrainPlugin,
+    timing::TimingPlugin,
+    healing::HealingPlugin,
+    grid::GridPlugin,
+    actions::ActionsPlugin,
+};
+
 const CLEAR: Color = Color::rgb(0.1, 0.1, 0.1);
 const RESOLUTION: f32 = 16.0 / 9.0;
 const WINDOWHEIGHT: f32 = 1080.;
 
-pub const TILESIZE: f32 = 0.1;
-pub const PLAYERSPEED: f32 = 2.5;
-pub const PLAYERSIZE: f32 = 0.9;
-pub const MINPROTECT: f32 = 0.5; // The duration in which the player is protected from encounters.
-pub const MAXPROTECT: f32 = 3.0;
-
-mod player;
-mod debug;
-mod ascii;
-mod tilemap;
-mod combat;
-mod fadeout;
-mod kolleg;
-
-use player::PlayerPlugin;
-use debug::DebugPlugin;
-use ascii::AsciiPlugin;
-use tilemap::TileMapPlugin;
-use combat::CombatPlugin;
-use fadeout::FadeoutPlugin;
-use kolleg::KollegPlugin;
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
-pub enum GameState {
-    Overworld,
-    Combat,
-}
-
 fn main() {
     let height: f32 = WINDOWHEIGHT;
 
@@ -61,6 +64,26 @@ fn main() {
         .add_plugin(CombatPlugin)
         .add_plugin(FadeoutPlugin)
         .add_plugin(KollegPlugin)
+        .add_plugin(HealthBarPlugin)
+        .add_plugin(PopupPlugin)
+        .add_plugin(ExperiencePlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(StatusPlugin)
+        .add_plugin(SkillsPlugin)
+        .add_plugin(CombatMenuPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(CombatLogPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(LootPlugin)
+        .add_plugin(BossPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(TimelinePlugin)
+        .add_plugin(TerrainPlugin)
+        .add_plugin(TimingPlugin)
+        .add_plugin(HealingPlugin)
+        .add_plugin(GridPlugin)
+        .add_plugin(ActionsPlugin)
         .run();
 }
 
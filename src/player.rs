@@ -1,13 +1,14 @@
-use std::time::Duration;
-
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use bevy::{prelude::*, ecs::system::SystemParam, sprite::collide_aabb::collide, math::const_vec3};
 use bevy_inspector_egui::Inspectable;
 use rand::Rng;
 
 use crate::{
-    PLAYERSPEED, TILESIZE, PLAYERSIZE, GameState, MINPROTECT, MAXPROTECT,
+    PLAYERSPEED, TILESIZE, PLAYERSIZE, GameState,
     ascii::{AsciiSheet, spawn_ascii_sprite},
-    tilemap::{TileCollider, EncounterSpawner, Map}, fadeout::{create_fadeout, FadeoutTimer}, combat::CombatStats,
+    tilemap::{TileCollider, EncounterSpawner, Map}, fadeout::Fader, combat::{AtbGauge, CombatStats, PartyMember},
+    experience::Experience, status::StatusEffects, skills::KnownSkills, element::Affinities,
+    inventory::Inventory, kolleg::PartyMenu, terrain::BattleTerrain, actions::{Action, Actions},
+    settings::{MovementMode, Settings}, tilemap::MapSettings,
 };
 
 #[derive(Component, Inspectable)]
@@ -16,7 +17,28 @@ pub struct Player {
     just_moved: bool,
 }
 
-pub struct VulnerabilityTimer(Timer);
+/// Tiles walked inside encounter zones since the last fight, a fight is rolled for each one.
+#[derive(Default)]
+pub struct EncounterSteps {
+    last_position: Option<Vec3>,
+    walked: f32, // Towards the next tile.
+    steps: usize,
+}
+
+pub const PLAYERSTART: Vec3 = const_vec3!([2.0 * TILESIZE, -2.0 * TILESIZE, 900.0]);
+
+/// The stats the player begins a new game with.
+pub fn starting_stats() -> CombatStats {
+    CombatStats {
+        health: 10,
+        max_health: 10,
+        attack: 2,
+        defense: 1,
+        mana: 5,
+        max_mana: 5,
+        speed: 5,
+    }
+}
 
 pub struct PlayerPlugin;
 
@@ -26,7 +48,7 @@ impl Plugin for PlayerPlugin {
             .add_system_set(SystemSet::on_enter(GameState::Overworld)
                 .with_system(show_player)
                 .with_system(show_map)
-                .with_system(set_encounter_timer)
+                .with_system(reset_encounter_steps)
             )
             .add_system_set(SystemSet::on_enter(GameState::Combat)
                 .with_system(hide_player)
@@ -38,17 +60,14 @@ impl Plugin for PlayerPlugin {
                 .with_system(player_movement)
                 .with_system(player_encounter_checking)
             )
-            .insert_resource(VulnerabilityTimer(Timer::from_seconds(MINPROTECT, false)))
+            .init_resource::<EncounterSteps>()
             .add_startup_system(spawn_player);
     }
 }
 
-fn set_encounter_timer(mut timer: ResMut<VulnerabilityTimer>) {
-    let mut rng = rand::thread_rng();
-    let secs = rng.gen_range(MINPROTECT..MAXPROTECT);
-    println!("{}", secs);
-    timer.0.set_duration(Duration::from_secs_f32(secs));
-    timer.0.reset();
+/// Every fight is followed by a few safe steps again.
+fn reset_encounter_steps(mut steps: ResMut<EncounterSteps>) {
+    *steps = EncounterSteps::default();
 }
 
 fn hide_map(
@@ -116,20 +135,42 @@ fn change_player_visability(
 fn player_encounter_checking(
     mut commands: Commands,
     player_query: Query<(&Player, &Transform)>,
-    encounter_query: Query<&Transform, (With<EncounterSpawner>, Without<Player>)>,
-    ascii: Res<AsciiSheet>,
-    mut timer: ResMut<VulnerabilityTimer>,
-    mut timer2: ResMut<FadeoutTimer>,
-    time: Res<Time>,
+    encounter_query: Query<(&Transform, &EncounterSpawner), Without<Player>>,
+    mut terrain: ResMut<BattleTerrain>,
+    mut steps: ResMut<EncounterSteps>,
+    mut fader: Fader,
+    settings: Res<Settings>,
 ) {
     let (player, player_transform) = player_query.single();
-    if player.just_moved
-        && encounter_query
-            .iter()
-            .any(|&transform| wall_collision_check(player_transform.translation, transform.translation)) {
-        if timer.0.tick(time.delta()).just_finished() {
-            create_fadeout(&mut commands, GameState::Combat, &ascii, &mut timer2);
+    let position = player_transform.translation;
+    let last_position = steps.last_position.replace(position);
+
+    // The overworld keeps running until the fade is halfway, no second fight may be rolled meanwhile.
+    if fader.is_fading() {
+        return;
+    }
+
+    let spawner = encounter_query
+        .iter()
+        .find(|(transform, _)| wall_collision_check(position, transform.translation));
+    let (spawner, last_position) = match (player.just_moved, spawner, last_position) {
+        (true, Some((_, spawner)), Some(last_position)) => (spawner, last_position),
+        _ => return,
+    };
+
+    // Counted by distance, so neither the frame rate nor the direction changes how often fights happen.
+    steps.walked += position.truncate().distance(last_position.truncate());
+    let mut rng = rand::thread_rng();
+    while steps.walked >= TILESIZE {
+        steps.walked -= TILESIZE;
+        steps.steps += 1;
+
+        if steps.steps > settings.safe_steps && rng.gen_bool(settings.encounter_rate) {
+            *steps = EncounterSteps::default();
+            terrain.0 = spawner.0;
+            fader.fade_to(&mut commands, GameState::Combat);
             println!("Changing to combat!");
+            return;
         }
     }
 }
@@ -145,39 +186,77 @@ fn camera_follow(
     camera_transform.translation.y = player_transform.translation.y;
 }
 
+/// One step of the tile movement mode, eased from `from` to `to`.
+struct TileStep {
+    from: Vec3,
+    to: Vec3,
+    progress: f32, // From 0 to 1.
+}
+
+impl TileStep {
+    /// Smoothstep, so every step starts and ends gently.
+    fn position(&self) -> Vec3 {
+        let eased = self.progress * self.progress * (3.0 - 2.0 * self.progress);
+        self.from.lerp(self.to, eased)
+    }
+}
+
+/// The movement mode in effect, a map may override the one from the settings.
+#[derive(SystemParam)]
+struct Movement<'w, 's> {
+    settings: Res<'w, Settings>,
+    map_settings: Res<'w, MapSettings>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl Movement<'_, '_> {
+    fn mode(&self) -> MovementMode {
+        self.map_settings.movement.unwrap_or(self.settings.movement)
+    }
+}
+
 fn player_movement(
     mut player_query: Query<(&mut Player, &mut Transform)>,
     wall_query: Query<&Transform, (With<TileCollider>, Without<Player>)>,
-    keyboard: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
+    party_menu: Res<PartyMenu>,
+    movement_mode: Movement,
     time: Res<Time>,
+    mut step: Local<Option<TileStep>>,
 ) {
     let (mut player, mut transform): (Mut<'_, Player>, Mut<'_, Transform>) = player_query.single_mut();
 
     player.just_moved = false;
 
     let mut sprintmodifier: f32 = 1.0;
-    if keyboard.pressed(KeyCode::LShift) {
+    if actions.pressed(Action::Sprint) {
         sprintmodifier = 2.0;
     }
 
+    // A step that was started is always finished, so the player never stops between two tiles.
+    if movement_mode.mode() == MovementMode::Tile || step.is_some() {
+        // Loading a save moves the player under our feet, a step from before is void then.
+        if let Some(current) = step.as_ref() {
+            if current.position().distance(transform.translation) > f32::EPSILON {
+                *step = None;
+            }
+        }
 
-    let mut delta_y: f32 = 0.0;
-    let mut delta_x: f32 = 0.0;
-
-    if keyboard.pressed(KeyCode::W) {
-        delta_y += player.speed * TILESIZE * time.delta_seconds() * sprintmodifier;
-    }
-    if keyboard.pressed(KeyCode::S) {
-        delta_y += -player.speed  * TILESIZE * time.delta_seconds() * sprintmodifier;
+        let movement = if party_menu.open { Vec2::ZERO } else { actions.movement() };
+        tile_movement(&mut player, &mut transform, &wall_query, &mut step, movement, sprintmodifier, time.delta_seconds());
+        return;
     }
-    if keyboard.pressed(KeyCode::A) {
-        delta_x += -player.speed * TILESIZE * time.delta_seconds() * sprintmodifier;
-    }
-    if keyboard.pressed(KeyCode::D) {
-        delta_x += player.speed * TILESIZE * time.delta_seconds() * sprintmodifier;
+
+    // The party menu has the keyboard while it is open.
+    if party_menu.open {
+        return;
     }
 
-    
+    // Keys always walk at full speed, a stick walks as fast as it leans.
+    let movement = actions.movement();
+    let delta_y: f32 = movement.y * player.speed * TILESIZE * time.delta_seconds() * sprintmodifier;
+    let delta_x: f32 = movement.x * player.speed * TILESIZE * time.delta_seconds() * sprintmodifier;
 
     let target = transform.translation + Vec3::new(delta_x, 0.0, 0.0);
 
@@ -194,12 +273,54 @@ fn player_movement(
     if !wall_query.iter().any(|&transform| wall_collision_check(target, transform.translation)) {
         transform.translation = target;
 
-        if delta_x != 0.0 {
+        if delta_y != 0.0 {
             player.just_moved = true;
         }
     }
 }
 
+/// Walks whole tiles, one per `1 / speed` seconds. Holding a direction keeps on stepping.
+fn tile_movement(
+    player: &mut Player,
+    transform: &mut Transform,
+    wall_query: &Query<&Transform, (With<TileCollider>, Without<Player>)>,
+    step: &mut Option<TileStep>,
+    movement: Vec2,
+    sprintmodifier: f32,
+    delta_seconds: f32,
+) {
+    if step.is_none() {
+        // No diagonals, the axis leaned on the most wins.
+        let direction = if movement.x.abs() >= movement.y.abs() && movement.x != 0.0 {
+            Vec3::new(movement.x.signum(), 0.0, 0.0)
+        } else if movement.y != 0.0 {
+            Vec3::new(0.0, movement.y.signum(), 0.0)
+        } else {
+            return;
+        };
+
+        // Free movement may have left the player between tiles.
+        let from = transform.translation;
+        let tile = Vec3::new((from.x / TILESIZE).round() * TILESIZE, (from.y / TILESIZE).round() * TILESIZE, from.z);
+        let to = tile + direction * TILESIZE;
+        if wall_query.iter().any(|&wall| wall_collision_check(to, wall.translation)) {
+            return;
+        }
+
+        *step = Some(TileStep { from, to, progress: 0.0 });
+    }
+
+    if let Some(current) = step {
+        current.progress = (current.progress + player.speed * sprintmodifier * delta_seconds).min(1.0);
+        transform.translation = current.position();
+        player.just_moved = true;
+
+        if current.progress >= 1.0 {
+            *step = None;
+        }
+    }
+}
+
 fn wall_collision_check(target_player_pos: Vec3, wall_translation: Vec3) -> bool {
     let collision = collide(
         target_player_pos,
@@ -218,7 +339,7 @@ fn spawn_player(mut commands: Commands, ascii: Res<AsciiSheet>) {
         &ascii,
         1,
         Color::rgb(0.3, 0.3, 0.9),
-        Vec3::new(2.0 * TILESIZE, -2.0 * TILESIZE, 900.0)
+        PLAYERSTART
     );
     
     let player = commands
@@ -228,12 +349,14 @@ fn spawn_player(mut commands: Commands, ascii: Res<AsciiSheet>) {
             speed: PLAYERSPEED,
             just_moved: false,
         })
-        .insert(CombatStats {
-            health: 10,
-            max_health: 10,
-            attack: 2,
-            defense: 1,
-        })
+        .insert(starting_stats())
+        .insert(Experience::default())
+        .insert(StatusEffects::default())
+        .insert(KnownSkills::default())
+        .insert(Affinities::default())
+        .insert(AtbGauge::default())
+        .insert(Inventory::default())
+        .insert(PartyMember)
         .id();
 
     // Creates a background for the player sprite.
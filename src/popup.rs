@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{
+    TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_text},
+};
+
+const POPUPDURATION: f32 = 1.0;
+const POPUPSPEED: f32 = 0.3; // How fast a popup rises, in screen units per second.
+const BANNERDURATION: f32 = 3.0;
+const BANNERHEIGHT: f32 = 0.6; // Where banners show up, relative to the camera.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PopupKind {
+    Damage,
+    Heal,
+    Miss,
+    Critical,
+}
+
+impl PopupKind {
+    fn color(&self) -> Color {
+        match self {
+            PopupKind::Damage => Color::rgb(0.9, 0.9, 0.9),
+            PopupKind::Heal => Color::rgb(0.3, 0.9, 0.3),
+            PopupKind::Miss => Color::rgb(0.5, 0.5, 0.5),
+            PopupKind::Critical => Color::rgb(0.95, 0.8, 0.1),
+        }
+    }
+
+    fn text(&self, amount: isize) -> String {
+        match self {
+            PopupKind::Damage => format!("{}", amount),
+            PopupKind::Heal => format!("+{}", amount),
+            PopupKind::Miss => "MISS".to_string(),
+            PopupKind::Critical => format!("{}!", amount),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Popup {
+    timer: Timer,
+    speed: f32,
+}
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(animate_popups);
+    }
+}
+
+/// Spawns a number above `position` that rises and fades out.
+pub fn spawn_popup(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    kind: PopupKind,
+    amount: isize,
+    position: Vec3,
+) -> Entity {
+    spawn_popup_text(commands, ascii, &kind.text(amount), kind.color(), position)
+}
+
+/// Like `spawn_popup`, but with any text.
+pub fn spawn_popup_text(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    text: &str,
+    color: Color,
+    position: Vec3,
+) -> Entity {
+    let left_center = position - Vec3::new((text.len() as f32 - 1.0) * TILESIZE / 2.0, 0.0, 0.0);
+
+    let popup = spawn_ascii_text(commands, ascii, text, color, left_center);
+    commands
+        .entity(popup)
+        .insert(Name::new("Popup"))
+        .insert(Popup {
+            timer: Timer::from_seconds(POPUPDURATION, false),
+            speed: POPUPSPEED,
+        });
+    popup
+}
+
+/// Shows a line of text at the top of the screen that stays put and fades out.
+pub fn spawn_banner(
+    commands: &mut Commands,
+    ascii: &AsciiSheet,
+    camera: Entity,
+    text: &str,
+    color: Color,
+) -> Entity {
+    let left_center = Vec3::new(-(text.len() as f32 - 1.0) * TILESIZE / 2.0, BANNERHEIGHT, -1.0);
+
+    let banner = spawn_ascii_text(commands, ascii, text, color, left_center);
+    commands
+        .entity(banner)
+        .insert(Name::new("Banner"))
+        .insert(Popup {
+            timer: Timer::from_seconds(BANNERDURATION, false),
+            speed: 0.0,
+        });
+    commands.entity(camera).push_children(&[banner]);
+    banner
+}
+
+fn animate_popups(
+    mut commands: Commands,
+    mut popup_query: Query<(Entity, &mut Popup, &mut Transform, &Children)>,
+    mut sprite_query: Query<&mut TextureAtlasSprite>,
+    time: Res<Time>,
+) {
+    for (entity, mut popup, mut transform, children) in popup_query.iter_mut() {
+        popup.timer.tick(time.delta());
+        transform.translation.y += popup.speed * time.delta_seconds();
+
+        for child in children.iter() {
+            if let Ok(mut sprite) = sprite_query.get_mut(*child) {
+                sprite.color.set_a(popup.timer.percent_left());
+            }
+        }
+
+        if popup.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
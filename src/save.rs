@@ -0,0 +1,206 @@
+use std::{fs::File, io::{self, BufRead, BufReader, Write}};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    boss::DefeatedBosses,
+    combat::{CombatStats, EnemyType},
+    kolleg::Kolleg,
+    experience::Experience,
+    inventory::Inventory,
+    player::{Player, starting_stats},
+};
+
+const SAVEFILE: &str = "save.txt";
+
+/// Everything about the player that survives a game over.
+pub struct SaveData {
+    pub stats: CombatStats,
+    pub level: usize,
+    pub experience: usize,
+    pub translation: Vec3,
+    pub inventory: Inventory,
+    pub defeated_bosses: Vec<(usize, usize)>,
+    pub kollegs: Vec<SavedKolleg>,
+}
+
+pub struct SavedKolleg {
+    pub enemy_type: EnemyType,
+    pub active: bool,
+    pub stats: CombatStats,
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Overworld).with_system(autosave));
+    }
+}
+
+fn autosave(
+    player_query: Query<(&CombatStats, &Experience, &Transform, &Inventory), With<Player>>,
+    kolleg_query: Query<(&Kolleg, &CombatStats), Without<Player>>,
+    defeated: Res<DefeatedBosses>,
+    mut started: Local<bool>,
+) {
+    // The first time is the game starting, that would overwrite the last save with the starting stats.
+    if !*started {
+        *started = true;
+        return;
+    }
+
+    let (stats, experience, transform, inventory) = player_query.single();
+
+    let data = SaveData {
+        stats: *stats,
+        level: experience.level,
+        experience: experience.points,
+        translation: transform.translation,
+        inventory: inventory.clone(),
+        defeated_bosses: defeated.0.clone(),
+        kollegs: kolleg_query
+            .iter()
+            .map(|(kolleg, stats)| SavedKolleg {
+                enemy_type: kolleg.enemy_type,
+                active: kolleg.active,
+                stats: *stats,
+            })
+            .collect(),
+    };
+
+    if let Err(error) = write_save(&data) {
+        println!("Could not save the game: {}", error);
+    }
+}
+
+pub fn write_save(data: &SaveData) -> io::Result<()> {
+    let mut file = File::create(SAVEFILE)?;
+
+    writeln!(file, "health={}", data.stats.health)?;
+    writeln!(file, "max_health={}", data.stats.max_health)?;
+    writeln!(file, "attack={}", data.stats.attack)?;
+    writeln!(file, "defense={}", data.stats.defense)?;
+    writeln!(file, "mana={}", data.stats.mana)?;
+    writeln!(file, "max_mana={}", data.stats.max_mana)?;
+    writeln!(file, "speed={}", data.stats.speed)?;
+    writeln!(file, "level={}", data.level)?;
+    writeln!(file, "experience={}", data.experience)?;
+    writeln!(file, "x={}", data.translation.x)?;
+    writeln!(file, "y={}", data.translation.y)?;
+    writeln!(file, "z={}", data.translation.z)?;
+    writeln!(file, "gold={}", data.inventory.gold)?;
+    for (item, count) in data.inventory.items.iter() {
+        writeln!(file, "item={}:{}", item, count)?;
+    }
+    for (x, y) in data.defeated_bosses.iter() {
+        writeln!(file, "boss={},{}", x, y)?;
+    }
+    for kolleg in data.kollegs.iter() {
+        let stats = kolleg.stats;
+        writeln!(
+            file,
+            "kolleg={}|{}|{}|{}|{}|{}|{}|{}|{}",
+            kolleg.enemy_type.name(),
+            kolleg.active,
+            stats.health,
+            stats.max_health,
+            stats.attack,
+            stats.defense,
+            stats.mana,
+            stats.max_mana,
+            stats.speed,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Reads the last save, returns `None` if there is none or it is broken.
+pub fn read_save() -> Option<SaveData> {
+    let file = File::open(SAVEFILE).ok()?;
+
+    // Saves from older versions lack the newer keys, those keep their starting values.
+    let mut data = SaveData {
+        stats: starting_stats(),
+        level: 1,
+        experience: 0,
+        translation: Vec3::ZERO,
+        inventory: Inventory::default(),
+        defeated_bosses: Vec::new(),
+        kollegs: Vec::new(),
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = line.ok()?;
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        match key.trim() {
+            "health" => data.stats.health = value.trim().parse().ok()?,
+            "max_health" => data.stats.max_health = value.trim().parse().ok()?,
+            "attack" => data.stats.attack = value.trim().parse().ok()?,
+            "defense" => data.stats.defense = value.trim().parse().ok()?,
+            "mana" => data.stats.mana = value.trim().parse().ok()?,
+            "max_mana" => data.stats.max_mana = value.trim().parse().ok()?,
+            "speed" => data.stats.speed = value.trim().parse().ok()?,
+            "level" => data.level = value.trim().parse().ok()?,
+            "experience" => data.experience = value.trim().parse().ok()?,
+            "x" => data.translation.x = value.trim().parse().ok()?,
+            "y" => data.translation.y = value.trim().parse().ok()?,
+            "z" => data.translation.z = value.trim().parse().ok()?,
+            "gold" => data.inventory.gold = value.trim().parse().ok()?,
+            "item" => {
+                let (item, count) = value.rsplit_once(':')?;
+                data.inventory.items.push((item.to_string(), count.trim().parse().ok()?));
+            }
+            "boss" => {
+                let (x, y) = value.split_once(',')?;
+                data.defeated_bosses.push((x.trim().parse().ok()?, y.trim().parse().ok()?));
+            }
+            "kolleg" => data.kollegs.push(parse_kolleg(value)?),
+            _ => println!("Unknown save entry: {}", key),
+        }
+    }
+
+    // A save without health would send the player straight back to the game over screen.
+    if data.stats.health <= 0 {
+        return None;
+    }
+
+    Some(data)
+}
+
+fn parse_kolleg(value: &str) -> Option<SavedKolleg> {
+    let fields: Vec<&str> = value.split('|').map(|field| field.trim()).collect();
+    if fields.len() < 2 || fields.len() > 9 {
+        return None;
+    }
+
+    let enemy_type = EnemyType::parse(fields[0])?;
+    let numbers: Vec<isize> = fields[2..].iter().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+
+    // Stats missing at the end of the line, from saves older than them, stay at the enemy's own.
+    let mut stats = enemy_type.stats();
+    let slots = [
+        &mut stats.health,
+        &mut stats.max_health,
+        &mut stats.attack,
+        &mut stats.defense,
+        &mut stats.mana,
+        &mut stats.max_mana,
+        &mut stats.speed,
+    ];
+    for (slot, number) in slots.into_iter().zip(numbers) {
+        *slot = number;
+    }
+
+    Some(SavedKolleg {
+        enemy_type,
+        active: fields[1].parse().ok()?,
+        stats,
+    })
+}
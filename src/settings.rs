@@ -0,0 +1,128 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+
+use bevy::prelude::*;
+
+use crate::timing::TimingWindow;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CombatMode {
+    TurnBased,
+    Atb, // Active time battle, everybody acts as soon as their gauge is full.
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MovementMode {
+    Free,
+    Tile, // Every step moves exactly one tile, like in classic RPGs.
+}
+
+impl MovementMode {
+    pub fn parse(name: &str) -> Option<MovementMode> {
+        match name {
+            "free" => Some(MovementMode::Free),
+            "tile" => Some(MovementMode::Tile),
+            _ => None,
+        }
+    }
+}
+
+/// Player facing options, loaded from `assets/settings.txt`.
+pub struct Settings {
+    pub combat_mode: CombatMode,
+    pub combat_seed: Option<u64>, // Makes every fight play out the same, for testing.
+    pub timed_hits: bool, // Attacks and hits on the party wait for a well timed Confirm.
+    pub attack_window: TimingWindow,
+    pub defense_window: TimingWindow,
+    pub grid_combat: bool, // Fights take place on a grid cut out of the map around the player.
+    pub stick_deadzone: f32, // How far a gamepad stick may drift before it counts as input.
+    pub movement: MovementMode, // Maps can ask for their own with `@movement = ...`.
+    pub encounter_rate: f64, // Chance of a fight per tile walked in an encounter zone.
+    pub safe_steps: usize, // Tiles walked after a fight before the next one can happen.
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            combat_mode: CombatMode::TurnBased,
+            combat_seed: None,
+            timed_hits: true,
+            attack_window: TimingWindow { open: 0.15, close: 0.35 },
+            defense_window: TimingWindow { open: 0.2, close: 0.4 },
+            grid_combat: false,
+            stick_deadzone: 0.2,
+            movement: MovementMode::Free,
+            encounter_rate: 0.2,
+            safe_steps: 2,
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_settings);
+    }
+}
+
+fn load_settings(mut commands: Commands) {
+    let mut settings = Settings::default();
+
+    // Without a settings file the defaults are just fine.
+    if let Ok(file) = File::open("assets/settings.txt") {
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    println!("Invalid setting: {}", line);
+                    continue;
+                }
+            };
+
+            match (key, value) {
+                ("combat_mode", "turn_based") => settings.combat_mode = CombatMode::TurnBased,
+                ("combat_mode", "atb") => settings.combat_mode = CombatMode::Atb,
+                ("combat_seed", seed) => match seed.parse() {
+                    Ok(seed) => settings.combat_seed = Some(seed),
+                    Err(_) => println!("Invalid combat seed: {}", seed),
+                },
+                ("timed_hits", "on") => settings.timed_hits = true,
+                ("timed_hits", "off") => settings.timed_hits = false,
+                ("grid_combat", "on") => settings.grid_combat = true,
+                ("grid_combat", "off") => settings.grid_combat = false,
+                ("movement", mode) => match MovementMode::parse(mode) {
+                    Some(mode) => settings.movement = mode,
+                    None => println!("Unknown movement mode: {}", mode),
+                },
+                ("encounter_rate", rate) => match rate.parse() {
+                    Ok(rate) if (0.0..=1.0).contains(&rate) => settings.encounter_rate = rate,
+                    _ => println!("Invalid encounter rate: {}", rate),
+                },
+                ("safe_steps", steps) => match steps.parse() {
+                    Ok(steps) => settings.safe_steps = steps,
+                    Err(_) => println!("Invalid safe steps: {}", steps),
+                },
+                ("stick_deadzone", deadzone) => match deadzone.parse() {
+                    Ok(deadzone) if (0.0..1.0).contains(&deadzone) => settings.stick_deadzone = deadzone,
+                    _ => println!("Invalid stick deadzone: {}", deadzone),
+                },
+                ("attack_window", window) => match TimingWindow::parse(window) {
+                    Some(window) => settings.attack_window = window,
+                    None => println!("Invalid timing window: {}", window),
+                },
+                ("defense_window", window) => match TimingWindow::parse(window) {
+                    Some(window) => settings.defense_window = window,
+                    None => println!("Invalid timing window: {}", window),
+                },
+                _ => println!("Unknown setting: {} = {}", key, value),
+            }
+        }
+    }
+
+    commands.insert_resource(settings);
+}
@@ -0,0 +1,131 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    combat_log::CombatLog,
+    element::DamageType,
+    experience::Experience,
+    player::Player,
+    status::StatusKind,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SkillTarget {
+    Enemy,
+    AllEnemies,
+    Myself,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SkillEffect {
+    Damage,
+    Heal,
+    Status(StatusKind),
+}
+
+pub struct Skill {
+    pub name: String,
+    pub cost: isize,
+    pub target: SkillTarget,
+    pub effect: SkillEffect,
+    pub damage_type: DamageType,
+    pub power: isize,
+    pub level: usize, // The level at which the player learns it.
+}
+
+/// Every skill in the game, as loaded from `assets/skills.txt`.
+pub struct SkillBook(pub Vec<Skill>);
+
+/// Indices into the `SkillBook` of the skills a combatant can use.
+#[derive(Component, Default)]
+pub struct KnownSkills(pub Vec<usize>);
+
+pub struct SkillsPlugin;
+
+impl Plugin for SkillsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_skills)
+            .add_system(learn_skills);
+    }
+}
+
+fn load_skills(mut commands: Commands) {
+    commands.insert_resource(read_skills());
+}
+
+/// Reads `assets/skills.txt`, the simulator fights with the same skills as the game.
+pub fn read_skills() -> SkillBook {
+    let file = File::open("assets/skills.txt").expect("No skills file found");
+    let mut skills = Vec::new();
+
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        skills.push(parse_skill(line).unwrap_or_else(|| panic!("Invalid skill: {}", line)));
+    }
+
+    SkillBook(skills)
+}
+
+fn parse_skill(line: &str) -> Option<Skill> {
+    let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+    if fields.len() != 7 {
+        return None;
+    }
+
+    let target = match fields[2] {
+        "enemy" => SkillTarget::Enemy,
+        "all" => SkillTarget::AllEnemies,
+        "self" => SkillTarget::Myself,
+        _ => return None,
+    };
+
+    let effect = match fields[3] {
+        "damage" => SkillEffect::Damage,
+        "heal" => SkillEffect::Heal,
+        "poison" => SkillEffect::Status(StatusKind::Poison),
+        "stun" => SkillEffect::Status(StatusKind::Stun),
+        "sleep" => SkillEffect::Status(StatusKind::Sleep),
+        "attack up" => SkillEffect::Status(StatusKind::AttackUp),
+        "defense down" => SkillEffect::Status(StatusKind::DefenseDown),
+        _ => return None,
+    };
+
+    Some(Skill {
+        name: fields[0].to_string(),
+        cost: fields[1].parse().ok()?,
+        target,
+        effect,
+        damage_type: DamageType::parse(fields[4])?,
+        power: fields[5].parse().ok()?,
+        level: fields[6].parse().ok()?,
+    })
+}
+
+type PlayerLeveled = (With<Player>, Changed<Experience>);
+
+fn learn_skills(
+    mut player_query: Query<(&Experience, &mut KnownSkills), PlayerLeveled>,
+    book: Res<SkillBook>,
+    state: Res<State<GameState>>,
+    mut log: ResMut<CombatLog>,
+) {
+    for (experience, mut known) in player_query.iter_mut() {
+        // Forgetting is needed when an older save is loaded.
+        known.0.retain(|&index| book.0[index].level <= experience.level);
+
+        for (index, skill) in book.0.iter().enumerate() {
+            if skill.level <= experience.level && !known.0.contains(&index) {
+                known.0.push(index);
+                // Experience is only earned in fights, outside of them skills are relearned from a save.
+                if *state.current() == GameState::Combat {
+                    log.push(format!("Learned {}!", skill.name));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use crate::{GameState, combat::CombatStats};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    Poison,
+    Stun,
+    Sleep,
+    AttackUp,
+    DefenseDown,
+    Guard,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poisoned",
+            StatusKind::Stun => "stunned",
+            StatusKind::Sleep => "asleep",
+            StatusKind::AttackUp => "pumped up",
+            StatusKind::DefenseDown => "exposed",
+            StatusKind::Guard => "guarding",
+        }
+    }
+
+    /// How many turns of the afflicted combatant the effect lasts.
+    fn duration(&self) -> usize {
+        match self {
+            StatusKind::Poison => 3,
+            StatusKind::Stun => 1,
+            StatusKind::Sleep => 3,
+            StatusKind::AttackUp => 3,
+            StatusKind::DefenseDown => 3,
+            StatusKind::Guard => 1, // Until the end of the guard's next turn.
+        }
+    }
+
+    /// The ascii index shown next to the health bar.
+    pub fn icon(&self) -> usize {
+        match self {
+            StatusKind::Poison => 'P' as usize,
+            StatusKind::Stun => '*' as usize,
+            StatusKind::Sleep => 'z' as usize,
+            StatusKind::AttackUp => 24, // Arrow up
+            StatusKind::DefenseDown => 25, // Arrow down
+            StatusKind::Guard => 'G' as usize,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            StatusKind::Poison => Color::rgb(0.5, 0.9, 0.2),
+            StatusKind::Stun => Color::rgb(0.95, 0.8, 0.1),
+            StatusKind::Sleep => Color::rgb(0.4, 0.5, 0.95),
+            StatusKind::AttackUp => Color::rgb(0.9, 0.3, 0.2),
+            StatusKind::DefenseDown => Color::rgb(0.3, 0.8, 0.9),
+            StatusKind::Guard => Color::rgb(0.7, 0.7, 0.8),
+        }
+    }
+}
+
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns_left: usize,
+    started: bool, // Effects that landed during someone's turn only count down from the afflicted's next one.
+}
+
+impl StatusEffect {
+    fn on_turn_start(&mut self, stats: &CombatStats, outcome: &mut TurnStart) {
+        self.started = true;
+        match self.kind {
+            StatusKind::Poison => outcome.damage += std::cmp::max(stats.max_health / 8, 1),
+            StatusKind::Stun | StatusKind::Sleep => outcome.skip_turn = true,
+            _ => (),
+        }
+    }
+
+    fn on_turn_end(&mut self) {
+        if self.started {
+            self.turns_left = self.turns_left.saturating_sub(1);
+        }
+    }
+
+    fn modify(&self, stats: &mut CombatStats) {
+        match self.kind {
+            StatusKind::AttackUp => stats.attack += std::cmp::max(stats.attack / 2, 1),
+            StatusKind::DefenseDown => {
+                stats.defense = std::cmp::max(stats.defense - std::cmp::max(stats.defense / 2, 1), 0)
+            }
+            StatusKind::Guard => stats.defense += std::cmp::max(stats.defense, 1),
+            _ => (),
+        }
+    }
+}
+
+/// What the active effects do to a combatant whose turn starts.
+#[derive(Default)]
+pub struct TurnStart {
+    pub damage: isize,
+    pub skip_turn: bool,
+}
+
+/// The status effects currently afflicting a combatant.
+#[derive(Component, Default)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    /// Adds an effect, or refreshes its duration if it is already active.
+    pub fn apply(&mut self, kind: StatusKind) {
+        match self.0.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                effect.turns_left = kind.duration();
+                effect.started = false;
+            }
+            None => self.0.push(StatusEffect {
+                kind,
+                turns_left: kind.duration(),
+                started: false,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, kind: StatusKind) {
+        self.0.retain(|effect| effect.kind != kind);
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn on_turn_start(&mut self, stats: &CombatStats) -> TurnStart {
+        let mut outcome = TurnStart::default();
+        for effect in self.0.iter_mut() {
+            effect.on_turn_start(stats, &mut outcome);
+        }
+        outcome
+    }
+
+    /// Counts down the effects that were there when the turn started and drops the ones that ran out.
+    pub fn on_turn_end(&mut self) {
+        for effect in self.0.iter_mut() {
+            effect.on_turn_end();
+        }
+        self.0.retain(|effect| effect.turns_left > 0);
+    }
+
+    /// The stats with all active effects applied.
+    pub fn modify(&self, stats: &CombatStats) -> CombatStats {
+        let mut modified = *stats;
+        for effect in self.0.iter() {
+            effect.modify(&mut modified);
+        }
+        modified
+    }
+}
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_exit(GameState::Combat).with_system(clear_status_effects));
+    }
+}
+
+// Effects do not outlast the fight they were applied in.
+fn clear_status_effects(mut effects_query: Query<&mut StatusEffects>) {
+    for mut effects in effects_query.iter_mut() {
+        effects.0.clear();
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::{prelude::*, math::const_vec2};
+
+use crate::{
+    GameState, TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_sprite},
+    combat::{CombatStats, Enemy},
+    combat_log::CombatLog,
+    element::DamageType,
+};
+
+const BACKDROPSIZE: Vec2 = const_vec2!([4.0, 2.0]); // A bit more than the combat camera sees.
+const TERRAINBOOST: isize = 1; // Extra attack and defense for enemies at home on the terrain.
+
+/// What the ground looks like where a fight breaks out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Terrain {
+    Plain,
+    Grass,
+    Water,
+}
+
+impl Terrain {
+    /// The terrain of encounter tiles drawn with `symbol` in the map file.
+    pub fn from_symbol(symbol: char) -> Option<Terrain> {
+        match symbol {
+            '"' => Some(Terrain::Grass),
+            '~' => Some(Terrain::Water),
+            _ => None,
+        }
+    }
+
+    fn backdrop_color(&self) -> Color {
+        match self {
+            Terrain::Plain => Color::rgb(0.1, 0.1, 0.1),
+            Terrain::Grass => Color::rgb(0.08, 0.2, 0.08),
+            Terrain::Water => Color::rgb(0.05, 0.1, 0.25),
+        }
+    }
+
+    /// The character scattered over the backdrop, plain ground stays empty.
+    fn decoration(&self) -> Option<(char, Color)> {
+        match self {
+            Terrain::Plain => None,
+            Terrain::Grass => Some(('"', Color::rgb(0.2, 0.5, 0.2))),
+            Terrain::Water => Some(('~', Color::rgb(0.3, 0.45, 0.8))),
+        }
+    }
+
+    /// Enemies attacking with this damage type fight better on this terrain.
+    pub fn boost(&self) -> Option<DamageType> {
+        match self {
+            Terrain::Plain => None,
+            Terrain::Grass => Some(DamageType::Earth),
+            Terrain::Water => Some(DamageType::Water),
+        }
+    }
+
+    /// Strengthens an enemy whose attacks match the terrain, returns whether it did.
+    pub fn empower(&self, enemy: &Enemy, stats: &mut CombatStats) -> bool {
+        if self.boost() != Some(enemy.attack_type) {
+            return false;
+        }
+
+        stats.attack += TERRAINBOOST;
+        stats.defense += TERRAINBOOST;
+        true
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Terrain::Plain => "ground",
+            Terrain::Grass => "grass",
+            Terrain::Water => "water",
+        }
+    }
+}
+
+/// The terrain of the current fight, set by whatever started it.
+pub struct BattleTerrain(pub Terrain);
+
+#[derive(Component)]
+struct Backdrop;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BattleTerrain(Terrain::Plain))
+            .add_system_set(SystemSet::on_enter(GameState::Combat).with_system(spawn_backdrop))
+            .add_system_set(SystemSet::on_update(GameState::Combat).with_system(boost_enemies))
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_backdrop));
+    }
+}
+
+fn spawn_backdrop(mut commands: Commands, ascii: Res<AsciiSheet>, terrain: Res<BattleTerrain>) {
+    let mut sprite = TextureAtlasSprite::new(0);
+    sprite.color = terrain.0.backdrop_color();
+    sprite.custom_size = Some(BACKDROPSIZE);
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite,
+            texture_atlas: ascii.0.clone(),
+            transform: Transform {
+                translation: Vec3::new(0.0, 0.0, 10.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("Backdrop"))
+        .insert(Backdrop);
+
+    let (symbol, color) = match terrain.0.decoration() {
+        Some(decoration) => decoration,
+        None => return,
+    };
+
+    // A fixed pattern rather than a random one, so the same terrain always looks the same.
+    let columns = (BACKDROPSIZE.x / TILESIZE) as usize;
+    let rows = (BACKDROPSIZE.y / TILESIZE) as usize;
+    for x in 0..columns {
+        for y in 0..rows {
+            if (x * 7 + y * 13) % 11 != 0 {
+                continue;
+            }
+
+            let translation = Vec3::new(
+                (x as f32 + 0.5) * TILESIZE - BACKDROPSIZE.x / 2.0,
+                (y as f32 + 0.5) * TILESIZE - BACKDROPSIZE.y / 2.0,
+                20.0,
+            );
+            let decoration = spawn_ascii_sprite(&mut commands, &ascii, symbol as usize, color, translation);
+            commands.entity(decoration).insert(Backdrop);
+        }
+    }
+}
+
+/// Strengthens every enemy, summons included, whose attacks match the terrain.
+fn boost_enemies(
+    mut enemy_query: Query<(&Enemy, &Name, &mut CombatStats), Added<Enemy>>,
+    terrain: Res<BattleTerrain>,
+    mut log: ResMut<CombatLog>,
+) {
+    for (enemy, name, mut stats) in enemy_query.iter_mut() {
+        if terrain.0.empower(enemy, &mut stats) {
+            log.push(format!("The {} empowers {}!", terrain.0.name(), name.as_str()));
+        }
+    }
+}
+
+fn despawn_backdrop(
+    mut commands: Commands,
+    mut terrain: ResMut<BattleTerrain>,
+    backdrop_query: Query<Entity, With<Backdrop>>,
+) {
+    // Fights that don't set a terrain, like boss fights, take place on plain ground.
+    terrain.0 = Terrain::Plain;
+    for entity in backdrop_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -2,23 +2,47 @@ use std::{fs::File, io::{BufReader, BufRead}};
 
 use bevy::prelude::*;
 
-use crate::{ascii::{AsciiSheet, spawn_ascii_sprite}, TILESIZE};
+use crate::{ascii::{AsciiSheet, spawn_ascii_sprite}, boss::{boss_for_symbol, spawn_boss}, settings::MovementMode, terrain::Terrain, TILESIZE};
 
 #[derive(Component)]
 pub struct Map;
 
+/// A tile where random fights break out, on the backdrop of its terrain.
 #[derive(Component)]
-pub struct EncounterSpawner;
+pub struct EncounterSpawner(pub Terrain);
 
 
 #[derive(Component)]
 pub struct TileCollider;
 
+/// An inn, standing on it fully heals the whole roster.
+#[derive(Component)]
+pub struct HealingTile;
+
+/// Options set by the map file itself, they win over the player's settings.
+#[derive(Default)]
+pub struct MapSettings {
+    pub movement: Option<MovementMode>,
+}
+
+impl MapSettings {
+    fn apply(&mut self, option: &str) {
+        match option.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("movement", mode)) => match MovementMode::parse(mode) {
+                Some(mode) => self.movement = Some(mode),
+                None => println!("Unknown movement mode: {}", mode),
+            },
+            _ => println!("Unknown map option: {}", option),
+        }
+    }
+}
+
 pub struct TileMapPlugin;
 
 impl Plugin for TileMapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(create_simple_map);
+        app.init_resource::<MapSettings>()
+            .add_startup_system(create_simple_map);
     }
 }
 
@@ -36,28 +60,51 @@ fn create_simple_map(mut commands: Commands, ascii: Res<AsciiSheet>) {
     let file = File::open("assets/map.txt").expect("No map file found");
     let mut tiles = Vec::new();
 
-    for (y, line) in BufReader::new(file).lines().enumerate() {
-        if let Ok(line) = line {
-            for (x, char) in line.chars().enumerate() {
-                let tile = spawn_ascii_sprite(
-                    &mut commands,
-                    &ascii,
-                    char as usize,
-                    Color::rgb(0.9, 0.9, 0.9),
-                    Vec3::new(x as f32 * TILESIZE, -(y as f32) * TILESIZE, 100.0)
-                );
-                if char == '#' {
-                    commands.entity(tile)
-                        .insert(TileCollider);
-                }
-                if char == '~' {
-                    commands.entity(tile).insert(EncounterSpawner);
+    // Lines starting with '@' set options for this map instead of holding tiles.
+    let mut map_settings = MapSettings::default();
+    let rows = BufReader::new(file).lines().map_while(Result::ok).filter(|line| match line.strip_prefix('@') {
+        Some(option) => {
+            map_settings.apply(option);
+            false
+        }
+        None => true,
+    });
+
+    for (y, line) in rows.enumerate() {
+        for (x, char) in line.chars().enumerate() {
+            // Bosses stand on plain floor and live outside the map, so they can be hidden on their own.
+            let char = match boss_for_symbol(char) {
+                Some(enemy_type) => {
+                    let translation = Vec3::new(x as f32 * TILESIZE, -(y as f32) * TILESIZE, 150.0);
+                    spawn_boss(&mut commands, &ascii, enemy_type, (x, y), translation);
+                    ' '
                 }
-                tiles.push(tile);
+                None => char,
+            };
+
+            let tile = spawn_ascii_sprite(
+                &mut commands,
+                &ascii,
+                char as usize,
+                Color::rgb(0.9, 0.9, 0.9),
+                Vec3::new(x as f32 * TILESIZE, -(y as f32) * TILESIZE, 100.0)
+            );
+            if char == '#' {
+                commands.entity(tile)
+                    .insert(TileCollider);
             }
+            if char == '+' {
+                commands.entity(tile).insert(HealingTile);
+            }
+            if let Some(terrain) = Terrain::from_symbol(char) {
+                commands.entity(tile).insert(EncounterSpawner(terrain));
+            }
+            tiles.push(tile);
         }
     }
 
+    commands.insert_resource(map_settings);
+
     // Create map entity
     commands.spawn()
         .insert(Name::new("Map"))
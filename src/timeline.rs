@@ -0,0 +1,77 @@
+use bevy::{prelude::*, math::const_vec3};
+
+use crate::{
+    GameState, TILESIZE,
+    ascii::{AsciiSheet, spawn_ascii_text},
+    combat::{Enemy, Timeline},
+};
+
+const TIMELINEPOSITION: Vec3 = const_vec3!([1.1, 0.9, 800.0]);
+
+#[derive(Component)]
+struct TimelineLine;
+
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Combat).with_system(draw_timeline))
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_timeline));
+    }
+}
+
+/// Lists who acts next in the top right corner, party members and enemies in their own colors.
+fn draw_timeline(
+    mut commands: Commands,
+    ascii: Res<AsciiSheet>,
+    timeline: Res<Timeline>,
+    combatant_query: Query<(&Name, Option<&Enemy>)>,
+    line_query: Query<Entity, With<TimelineLine>>,
+) {
+    if !timeline.is_changed() {
+        return;
+    }
+
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+    if timeline.0.is_empty() {
+        return;
+    }
+
+    let title = spawn_ascii_text(&mut commands, &ascii, "TURN ORDER", Color::rgb(0.9, 0.9, 0.9), TIMELINEPOSITION);
+    commands.entity(title).insert(TimelineLine);
+
+    for (i, entity) in timeline.0.iter().enumerate() {
+        let (name, enemy) = match combatant_query.get(*entity) {
+            Ok(combatant) => combatant,
+            Err(_) => continue,
+        };
+
+        let cursor = if i == 0 { '>' } else { ' ' };
+        let color = match enemy {
+            Some(_) => Color::rgb(0.9, 0.4, 0.3),
+            None => Color::rgb(0.4, 0.6, 0.95),
+        };
+
+        let line = spawn_ascii_text(
+            &mut commands,
+            &ascii,
+            &format!("{}{}", cursor, name.as_str()),
+            color,
+            TIMELINEPOSITION - Vec3::new(0.0, (i + 1) as f32 * TILESIZE * 1.2, 0.0),
+        );
+        commands.entity(line).insert(TimelineLine);
+    }
+}
+
+fn despawn_timeline(
+    mut commands: Commands,
+    mut timeline: ResMut<Timeline>,
+    line_query: Query<Entity, With<TimelineLine>>,
+) {
+    timeline.0.clear();
+    for line in line_query.iter() {
+        commands.entity(line).despawn_recursive();
+    }
+}
@@ -0,0 +1,220 @@
+use bevy::{prelude::*, math::const_vec3};
+
+use crate::{
+    GameState,
+    actions::{Action, Actions},
+    ascii::{AsciiSheet, spawn_ascii_text},
+};
+
+const PROMPTPOSITION: Vec3 = const_vec3!([-0.8, -0.1, 870.0]);
+const WAITCOLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+const OPENCOLOR: Color = Color::rgb(0.95, 0.8, 0.1);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimingKind {
+    Attack,  // A good press adds bonus damage.
+    Defense, // A good press blocks part of the hit.
+}
+
+/// When a press counts, in seconds after the prompt appears.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimingWindow {
+    pub open: f32,
+    pub close: f32,
+}
+
+impl TimingWindow {
+    /// Reads a window written as `open-close`, e.g. `0.15-0.3`.
+    pub fn parse(text: &str) -> Option<TimingWindow> {
+        let (open, close) = text.split_once('-')?;
+        let window = TimingWindow {
+            open: open.trim().parse().ok()?,
+            close: close.trim().parse().ok()?,
+        };
+
+        if window.open < 0.0 || window.close < window.open {
+            return None;
+        }
+        Some(window)
+    }
+}
+
+/// One timed press, lives on the prompt entity until combat collects the verdict.
+/// It only sees the time and whether Confirm was pressed, so it can be driven by simulated input.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ActionCommand {
+    pub kind: TimingKind,
+    window: TimingWindow,
+    elapsed: f32,
+    pressed_at: Option<f32>,
+    verdict: Option<bool>,
+}
+
+impl ActionCommand {
+    pub fn new(kind: TimingKind, window: TimingWindow) -> Self {
+        ActionCommand {
+            kind,
+            window,
+            elapsed: 0.0,
+            pressed_at: None,
+            verdict: None,
+        }
+    }
+
+    /// Advances by `delta` seconds. Only the first press counts, so mashing is too early.
+    pub fn update(&mut self, delta: f32, pressed: bool) {
+        if self.verdict.is_some() {
+            return;
+        }
+
+        self.elapsed += delta;
+        if pressed && self.pressed_at.is_none() {
+            self.pressed_at = Some(self.elapsed);
+        }
+
+        if let Some(time) = self.pressed_at {
+            self.verdict = Some(time >= self.window.open && time <= self.window.close);
+        } else if self.elapsed > self.window.close {
+            self.verdict = Some(false);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.verdict.is_none() && self.elapsed >= self.window.open
+    }
+
+    /// Whether the press was in time, once that is decided.
+    pub fn verdict(&self) -> Option<bool> {
+        self.verdict
+    }
+
+    /// The damage after the press was judged.
+    pub fn timed_damage(&self, damage: isize) -> isize {
+        match (self.kind, self.verdict) {
+            (TimingKind::Attack, Some(true)) => damage + std::cmp::max(damage / 2, 1),
+            (TimingKind::Defense, Some(true)) => damage / 2,
+            _ => damage,
+        }
+    }
+}
+
+pub struct TimingPlugin;
+
+impl Plugin for TimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Combat)
+                .with_system(run_action_commands.label("action_commands").before("combat_log"))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Combat).with_system(despawn_prompts));
+    }
+}
+
+/// Shows the prompt for a timed press of `key`, the command lives on the returned entity.
+pub fn spawn_prompt(commands: &mut Commands, ascii: &AsciiSheet, key: &str, command: ActionCommand) -> Entity {
+    let text = match command.kind {
+        TimingKind::Attack => format!("{} TO STRIKE!", key),
+        TimingKind::Defense => format!("{} TO BLOCK!", key),
+    };
+
+    let prompt = spawn_ascii_text(commands, ascii, &text, WAITCOLOR, PROMPTPOSITION);
+    commands.entity(prompt).insert(command);
+    prompt
+}
+
+fn run_action_commands(
+    mut actions: ResMut<Actions>,
+    mut command_query: Query<(&mut ActionCommand, &Children)>,
+    mut char_query: Query<&mut TextureAtlasSprite>,
+    time: Res<Time>,
+) {
+    if command_query.is_empty() {
+        return;
+    }
+
+    let pressed = actions.just_pressed(Action::Confirm);
+    for (mut command, children) in command_query.iter_mut() {
+        command.update(time.delta_seconds(), pressed);
+
+        let color = if command.is_open() { OPENCOLOR } else { WAITCOLOR };
+        for child in children.iter() {
+            if let Ok(mut sprite) = char_query.get_mut(*child) {
+                sprite.color = color;
+            }
+        }
+    }
+
+    // Confirm is eaten here while a prompt is up, so it does not also skip the log.
+    actions.clear_just_pressed(Action::Confirm);
+}
+
+fn despawn_prompts(mut commands: Commands, prompt_query: Query<Entity, With<ActionCommand>>) {
+    for prompt in prompt_query.iter() {
+        commands.entity(prompt).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: TimingWindow = TimingWindow { open: 0.25, close: 0.5 };
+    const STEP: f32 = 0.125;
+
+    /// Runs a command for one step per entry, pressing on the steps marked `true`.
+    fn run(kind: TimingKind, presses: &[bool]) -> ActionCommand {
+        let mut command = ActionCommand::new(kind, WINDOW);
+        for &pressed in presses {
+            command.update(STEP, pressed);
+        }
+        command
+    }
+
+    #[test]
+    fn early_press_fails() {
+        let command = run(TimingKind::Attack, &[true]);
+        assert_eq!(command.verdict(), Some(false));
+        assert_eq!(command.timed_damage(4), 4);
+    }
+
+    #[test]
+    fn press_in_window_succeeds() {
+        let strike = run(TimingKind::Attack, &[false, false, true]);
+        assert_eq!(strike.verdict(), Some(true));
+        assert_eq!(strike.timed_damage(4), 6);
+
+        let block = run(TimingKind::Defense, &[false, false, true]);
+        assert_eq!(block.verdict(), Some(true));
+        assert_eq!(block.timed_damage(4), 2);
+    }
+
+    #[test]
+    fn late_or_missing_press_fails() {
+        let waiting = run(TimingKind::Defense, &[false, false, false, false]);
+        assert_eq!(waiting.verdict(), None);
+        assert!(waiting.is_open());
+
+        let late = run(TimingKind::Defense, &[false, false, false, false, true]);
+        assert_eq!(late.verdict(), Some(false));
+        assert_eq!(late.timed_damage(4), 4);
+
+        let missed = run(TimingKind::Attack, &[false; 5]);
+        assert_eq!(missed.verdict(), Some(false));
+        assert!(!missed.is_open());
+    }
+
+    #[test]
+    fn only_the_first_press_counts() {
+        let command = run(TimingKind::Attack, &[true, true, true, true]);
+        assert_eq!(command.verdict(), Some(false));
+        assert_eq!(command.timed_damage(4), 4);
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(TimingWindow::parse("0.15-0.3"), Some(TimingWindow { open: 0.15, close: 0.3 }));
+        assert_eq!(TimingWindow::parse(" 0.2 - 0.2 "), Some(TimingWindow { open: 0.2, close: 0.2 }));
+        assert_eq!(TimingWindow::parse("0.3-0.15"), None);
+        assert_eq!(TimingWindow::parse("0.3"), None);
+        assert_eq!(TimingWindow::parse("soon-later"), None);
+    }
+}